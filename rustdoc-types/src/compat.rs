@@ -0,0 +1,812 @@
+//! Support for JSON produced by newer versions of rustdoc.
+//!
+//! Newer formats use externally tagged enums, integer ids and reworked shapes for a lot of items
+//! (`Path`, `DynTrait`, `FunctionSignature`, `StructKind`, ...). Instead of keeping a second copy
+//! of the schema around, the JSON tree is rewritten into the shape of [`FORMAT_VERSION`] and then
+//! deserialized with the regular types.
+//!
+//! The rewrite is lenient: anything it doesn't recognise is either passed through untouched or
+//! dropped, and deserialization reports what is still wrong.
+//!
+//! [`FORMAT_VERSION`]: crate::FORMAT_VERSION
+
+use std::collections::{HashMap, HashSet};
+
+use serde_json::{json, Map, Value};
+
+/// The oldest format version (the first one with externally tagged items) that can be upgraded.
+pub const MIN_VERSION: u32 = 28;
+/// The newest format version the upgrade was written against. Newer versions are still tried.
+pub const MAX_VERSION: u32 = 57;
+
+/// Rewrites a newer rustdoc JSON blob into the shape of the current format.
+pub fn upgrade(mut krate: Value) -> Value {
+    let index = match take(&mut krate, &["index"]) {
+        Value::Object(index) => index,
+        _ => Map::new(),
+    };
+    let cx = Context::new(&index);
+    let mut new_index: Map<String, Value> = index
+        .into_iter()
+        .filter_map(|(id, item)| Some((id, upgrade_item(item, &cx)?)))
+        .collect();
+
+    // modules must not list items that couldn't be represented (primitives, keywords, ...)
+    let kept: HashSet<String> = new_index.keys().cloned().collect();
+    for item in new_index.values_mut() {
+        if item["kind"] != "module" {
+            continue;
+        }
+        if let Some(Value::Array(items)) = item["inner"].get_mut("items") {
+            items.retain(|id| id.as_str().is_some_and(|id| kept.contains(id)));
+        }
+    }
+
+    let paths = match take(&mut krate, &["paths"]) {
+        Value::Object(paths) => paths
+            .into_iter()
+            .filter_map(|(id, mut summary)| {
+                let kind = item_kind(summary.get("kind")?.as_str()?)?;
+                summary["kind"] = json!(kind);
+                Some((id, summary))
+            })
+            .collect(),
+        _ => Map::new(),
+    };
+
+    if let Some(krate) = krate.as_object_mut() {
+        let root = krate.remove("root").map(id).unwrap_or(Value::Null);
+        krate.insert("root".into(), root);
+        krate.insert("index".into(), Value::Object(new_index));
+        krate.insert("paths".into(), Value::Object(paths));
+    }
+    krate
+}
+
+/// What is needed from the rest of the index to upgrade a single item.
+struct Context {
+    /// Tuple variants refer to their fields by id, but the current format stores the types inline.
+    fields: HashMap<String, Value>,
+    /// Associated types in impls used to be typedefs.
+    impl_items: HashSet<String>,
+}
+
+impl Context {
+    fn new(index: &Map<String, Value>) -> Self {
+        let inners = || {
+            index
+                .iter()
+                .filter_map(|(id, item)| Some((id, item.get("inner")?)))
+        };
+        let fields = inners()
+            .filter_map(|(id, inner)| Some((id.clone(), inner.get("struct_field")?.clone())))
+            .collect();
+        let impl_items = inners()
+            .filter_map(|(_, inner)| inner.get("impl")?.get("items")?.as_array())
+            .flatten()
+            .filter_map(|i| id(i.clone()).as_str().map(String::from))
+            .collect();
+        Self { fields, impl_items }
+    }
+}
+
+fn upgrade_item(mut item: Value, cx: &Context) -> Option<Value> {
+    let (kind, inner) = match take(&mut item, &["inner"]) {
+        Value::Object(inner) if inner.len() == 1 => inner.into_iter().next()?,
+        _ => return None,
+    };
+    let item_id = item.get("id").cloned().map(id)?;
+    let in_impl = item_id.as_str().is_some_and(|i| cx.impl_items.contains(i));
+    let (kind, inner) = upgrade_inner(&kind, inner, in_impl, cx)?;
+
+    let obj = item.as_object_mut()?;
+    obj.insert("id".into(), item_id);
+    if let Some(Value::Object(vis)) = obj.get_mut("visibility") {
+        if let Some(restricted) = vis.get_mut("restricted") {
+            let parent = take(restricted, &["parent"]);
+            restricted["parent"] = id(parent);
+        }
+    }
+    if let Some(Value::Object(links)) = obj.get_mut("links") {
+        for link in links.values_mut() {
+            *link = id(link.take());
+        }
+    }
+    let attrs = match obj.remove("attrs") {
+        Some(Value::Array(attrs)) => attrs.into_iter().map(attr).collect(),
+        _ => Vec::new(),
+    };
+    obj.insert("attrs".into(), json!(attrs));
+    obj.insert("kind".into(), json!(kind));
+    obj.insert("inner".into(), inner);
+    Some(item)
+}
+
+fn upgrade_inner(
+    kind: &str,
+    mut inner: Value,
+    in_impl: bool,
+    cx: &Context,
+) -> Option<(&'static str, Value)> {
+    let upgraded = match kind {
+        "module" => (
+            "module",
+            json!({
+                "is_crate": boolean(&mut inner, &["is_crate"]),
+                "items": ids(take(&mut inner, &["items"])),
+            }),
+        ),
+        "extern_crate" => (
+            "extern_crate",
+            json!({
+                "name": take(&mut inner, &["name"]),
+                "rename": take(&mut inner, &["rename"]),
+            }),
+        ),
+        "use" | "import" => (
+            "import",
+            json!({
+                "span": take(&mut inner, &["source", "span"]),
+                "name": take(&mut inner, &["name"]),
+                "id": opt(take(&mut inner, &["id"]), id),
+                "glob": boolean(&mut inner, &["is_glob", "glob"]),
+            }),
+        ),
+        "union" => (
+            "union",
+            json!({
+                "generics": generics(take(&mut inner, &["generics"])),
+                "fields_stripped": boolean(&mut inner, &["has_stripped_fields", "fields_stripped"]),
+                "fields": ids(take(&mut inner, &["fields"])),
+                "impls": ids(take(&mut inner, &["impls"])),
+            }),
+        ),
+        "struct" => {
+            let (struct_type, fields, stripped) = match take(&mut inner, &["kind"]) {
+                Value::Object(kind) => match kind.into_iter().next() {
+                    Some((k, Value::Array(fields))) if k == "tuple" => {
+                        let stripped = fields.iter().any(Value::is_null);
+                        let fields = fields.into_iter().filter(|f| !f.is_null()).collect();
+                        ("tuple", ids(Value::Array(fields)), stripped)
+                    }
+                    Some((_, mut plain)) => (
+                        "plain",
+                        ids(take(&mut plain, &["fields"])),
+                        boolean(&mut plain, &["has_stripped_fields", "fields_stripped"])
+                            == Value::Bool(true),
+                    ),
+                    None => ("unit", json!([]), false),
+                },
+                Value::String(_) => ("unit", json!([]), false),
+                // older formats still had the flat shape
+                _ => (
+                    match take(&mut inner, &["struct_type"]).as_str() {
+                        Some("tuple") => "tuple",
+                        Some("unit") => "unit",
+                        _ => "plain",
+                    },
+                    ids(take(&mut inner, &["fields"])),
+                    boolean(&mut inner, &["fields_stripped"]) == Value::Bool(true),
+                ),
+            };
+            (
+                "struct",
+                json!({
+                    "struct_type": struct_type,
+                    "generics": generics(take(&mut inner, &["generics"])),
+                    "fields_stripped": stripped,
+                    "fields": fields,
+                    "impls": ids(take(&mut inner, &["impls"])),
+                }),
+            )
+        }
+        "struct_field" => ("struct_field", ty(inner)),
+        "enum" => (
+            "enum",
+            json!({
+                "generics": generics(take(&mut inner, &["generics"])),
+                "variants_stripped": boolean(&mut inner, &["has_stripped_variants", "variants_stripped"]),
+                "variants": ids(take(&mut inner, &["variants"])),
+                "impls": ids(take(&mut inner, &["impls"])),
+            }),
+        ),
+        "variant" => ("variant", variant(take(&mut inner, &["kind"]), &cx.fields)),
+        "function" | "method" => ("function", function(inner)),
+        "trait" => (
+            "trait",
+            json!({
+                "is_auto": boolean(&mut inner, &["is_auto"]),
+                "is_unsafe": boolean(&mut inner, &["is_unsafe"]),
+                "items": ids(take(&mut inner, &["items"])),
+                "generics": generics(take(&mut inner, &["generics"])),
+                "bounds": bounds(take(&mut inner, &["bounds"])),
+                "implementors": ids(take(&mut inner, &["implementations", "implementors"])),
+            }),
+        ),
+        "trait_alias" => (
+            "trait_alias",
+            json!({
+                "generics": generics(take(&mut inner, &["generics"])),
+                "params": bounds(take(&mut inner, &["params"])),
+            }),
+        ),
+        "impl" => (
+            "impl",
+            json!({
+                "is_unsafe": boolean(&mut inner, &["is_unsafe"]),
+                "generics": generics(take(&mut inner, &["generics"])),
+                "provided_trait_methods": list(take(&mut inner, &["provided_trait_methods"]), Some),
+                "trait": opt(take(&mut inner, &["trait"]), path),
+                "for": ty(take(&mut inner, &["for"])),
+                "items": ids(take(&mut inner, &["items"])),
+                "negative": boolean(&mut inner, &["is_negative", "negative"]),
+                "synthetic": boolean(&mut inner, &["is_synthetic", "synthetic"]),
+                "blanket_impl": opt(take(&mut inner, &["blanket_impl"]), ty),
+            }),
+        ),
+        "type_alias" | "typedef" => (
+            "typedef",
+            json!({
+                "type": ty(take(&mut inner, &["type"])),
+                "generics": generics(take(&mut inner, &["generics"])),
+            }),
+        ),
+        "opaque_ty" => (
+            "opaque_ty",
+            json!({
+                "bounds": bounds(take(&mut inner, &["bounds"])),
+                "generics": generics(take(&mut inner, &["generics"])),
+            }),
+        ),
+        "constant" => {
+            let type_ = ty(take(&mut inner, &["type"]));
+            // newer formats nest the value in a `const` object
+            let c = match take(&mut inner, &["const"]) {
+                Value::Null => inner,
+                c => c,
+            };
+            ("constant", constant(type_, c))
+        }
+        "static" => (
+            "static",
+            json!({
+                "type": ty(take(&mut inner, &["type"])),
+                "mutable": boolean(&mut inner, &["is_mutable", "mutable"]),
+                "expr": string(take(&mut inner, &["expr"])),
+            }),
+        ),
+        "extern_type" | "foreign_type" => ("foreign_type", Value::Null),
+        "macro" => ("macro", inner),
+        "proc_macro" => {
+            let kind = match inner.get("kind").and_then(Value::as_str) {
+                Some("attr") => "proc_attribute",
+                Some("derive") => "proc_derive",
+                _ => "macro",
+            };
+            (kind, inner)
+        }
+        "assoc_const" => (
+            "assoc_const",
+            json!({
+                "type": ty(take(&mut inner, &["type"])),
+                "default": take(&mut inner, &["value", "default"]),
+            }),
+        ),
+        "assoc_type" if in_impl => (
+            "typedef",
+            json!({
+                "type": ty(take(&mut inner, &["type", "default"])),
+                "generics": generics(take(&mut inner, &["generics"])),
+            }),
+        ),
+        "assoc_type" => (
+            "assoc_type",
+            json!({
+                "bounds": bounds(take(&mut inner, &["bounds"])),
+                "default": opt(take(&mut inner, &["type", "default"]), ty),
+            }),
+        ),
+        // primitives, keywords and anything newer have no counterpart
+        _ => return None,
+    };
+    Some(upgraded)
+}
+
+fn variant(kind: Value, fields: &HashMap<String, Value>) -> Value {
+    match kind {
+        Value::Object(kind) => match kind.into_iter().next() {
+            Some((k, Value::Array(tuple))) if k == "tuple" => {
+                let tys: Vec<Value> = tuple
+                    .into_iter()
+                    .map(|f| match id(f).as_str().and_then(|f| fields.get(f)) {
+                        Some(field) => ty(field.clone()),
+                        // stripped field
+                        None => json!({ "kind": "infer" }),
+                    })
+                    .collect();
+                json!({ "variant_kind": "tuple", "variant_inner": tys })
+            }
+            Some((_, mut fields)) => json!({
+                "variant_kind": "struct",
+                "variant_inner": ids(take(&mut fields, &["fields"])),
+            }),
+            None => json!({ "variant_kind": "plain" }),
+        },
+        _ => json!({ "variant_kind": "plain" }),
+    }
+}
+
+fn function(mut f: Value) -> Value {
+    let mut header = take(&mut f, &["header"]);
+    let abi = match take(&mut header, &["abi"]) {
+        Value::Null => take(&mut f, &["abi"]),
+        abi => abi,
+    };
+    json!({
+        "decl": decl(take(&mut f, &["sig", "decl"])),
+        "generics": generics(take(&mut f, &["generics"])),
+        "header": qualifiers(header),
+        "abi": abi_string(abi),
+    })
+}
+
+fn decl(mut d: Value) -> Value {
+    let inputs: Vec<Value> = match take(&mut d, &["inputs"]) {
+        Value::Array(inputs) => inputs
+            .into_iter()
+            .filter_map(|input| match input {
+                Value::Array(mut pair) if pair.len() == 2 => {
+                    let t = ty(pair.pop()?);
+                    Some(json!([pair.pop()?, t]))
+                }
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    json!({
+        "inputs": inputs,
+        "output": opt(take(&mut d, &["output"]), ty),
+        "c_variadic": boolean(&mut d, &["is_c_variadic", "c_variadic"]),
+    })
+}
+
+fn qualifiers(mut header: Value) -> Value {
+    // already a list of qualifiers
+    if header.is_array() {
+        return header;
+    }
+    let mut quals = Vec::new();
+    for (keys, qual) in [
+        (["is_const", "const", "const_"], "const"),
+        (["is_unsafe", "unsafe", "unsafe_"], "unsafe"),
+        (["is_async", "async", "async_"], "async"),
+    ]
+    .iter()
+    {
+        if boolean(&mut header, keys) == Value::Bool(true) {
+            quals.push(json!(qual));
+        }
+    }
+    Value::Array(quals)
+}
+
+/// Formats an abi the way the current format does, i.e. as a quoted string: `"\"C\""`.
+fn abi_string(abi: Value) -> Value {
+    let (name, unwind) = match abi {
+        Value::String(name) => (name, false),
+        Value::Object(abi) => match abi.into_iter().next() {
+            Some((name, Value::String(other))) if name == "Other" => (other, false),
+            Some((name, opts)) => {
+                let unwind = opts.get("unwind").and_then(Value::as_bool).unwrap_or(false);
+                (name, unwind)
+            }
+            None => ("Rust".into(), false),
+        },
+        _ => ("Rust".into(), false),
+    };
+    let name = match name.trim_matches('"') {
+        "Rust" => "Rust".to_string(),
+        "C" => "C".to_string(),
+        other => other.to_lowercase(),
+    };
+    if unwind {
+        json!(format!("\"{}-unwind\"", name))
+    } else {
+        json!(format!("\"{}\"", name))
+    }
+}
+
+fn generics(mut g: Value) -> Value {
+    json!({
+        "params": list(take(&mut g, &["params"]), |p| Some(param(p))),
+        "where_predicates": list(take(&mut g, &["where_predicates"]), predicate),
+    })
+}
+
+fn param(mut p: Value) -> Value {
+    let kind = match take(&mut p, &["kind"]) {
+        Value::Object(kind) => match kind.into_iter().next() {
+            Some((k, mut t)) if k == "type" => json!({
+                "type": {
+                    "bounds": bounds(take(&mut t, &["bounds"])),
+                    "default": opt(take(&mut t, &["default"]), ty),
+                }
+            }),
+            Some((k, mut c)) if k == "const" => {
+                let type_ = match take(&mut c, &["type"]) {
+                    Value::Null => c,
+                    type_ => type_,
+                };
+                json!({ "const": ty(type_) })
+            }
+            _ => json!("lifetime"),
+        },
+        _ => json!("lifetime"),
+    };
+    json!({ "name": take(&mut p, &["name"]), "kind": kind })
+}
+
+fn predicate(p: Value) -> Option<Value> {
+    let (kind, mut p) = match p {
+        Value::Object(p) => p.into_iter().next()?,
+        _ => return None,
+    };
+    let p = match kind.as_str() {
+        "bound_predicate" => json!({
+            "bound_predicate": {
+                "ty": ty(take(&mut p, &["type", "ty"])),
+                "bounds": bounds(take(&mut p, &["bounds"])),
+                "generic_params": list(take(&mut p, &["generic_params"]), |p| Some(param(p))),
+            }
+        }),
+        "lifetime_predicate" | "region_predicate" => {
+            let bounds = match take(&mut p, &["outlives"]) {
+                Value::Array(outlives) => outlives
+                    .into_iter()
+                    .map(|lt| json!({ "outlives": lt }))
+                    .collect(),
+                _ => bounds(take(&mut p, &["bounds"])),
+            };
+            json!({
+                "region_predicate": {
+                    "lifetime": take(&mut p, &["lifetime"]),
+                    "bounds": bounds,
+                }
+            })
+        }
+        "eq_predicate" => json!({
+            "eq_predicate": {
+                "lhs": ty(take(&mut p, &["lhs"])),
+                "rhs": term(take(&mut p, &["rhs"])),
+            }
+        }),
+        _ => return None,
+    };
+    Some(p)
+}
+
+fn bounds(b: Value) -> Value {
+    list(b, |b| {
+        let (kind, mut b) = match b {
+            Value::Object(b) => b.into_iter().next()?,
+            _ => return None,
+        };
+        match kind.as_str() {
+            "trait_bound" => Some(json!({
+                "trait_bound": {
+                    "trait": path(take(&mut b, &["trait"])),
+                    "generic_params": list(take(&mut b, &["generic_params"]), |p| Some(param(p))),
+                    "modifier": match take(&mut b, &["modifier"]) {
+                        Value::Null => json!("none"),
+                        m => m,
+                    },
+                }
+            })),
+            "outlives" => Some(json!({ "outlives": b })),
+            // precise capturing (`use<'a, T>`) has no counterpart
+            _ => None,
+        }
+    })
+}
+
+/// Converts a `Path` into a `Type::ResolvedPath`.
+fn path(mut p: Value) -> Value {
+    if p.get("resolved_path").is_some() || p.get("kind").is_some() {
+        return ty(p);
+    }
+    json!({
+        "kind": "resolved_path",
+        "inner": {
+            "name": string(take(&mut p, &["path", "name"])),
+            "id": id(take(&mut p, &["id"])),
+            "args": opt(take(&mut p, &["args"]), generic_args),
+            "param_names": [],
+        }
+    })
+}
+
+fn ty(t: Value) -> Value {
+    let (kind, mut t) = match t {
+        Value::String(kind) => (kind, Value::Null),
+        // already in the current shape
+        Value::Object(t) if t.contains_key("kind") => return Value::Object(t),
+        Value::Object(t) if t.len() == 1 => t.into_iter().next().unwrap(),
+        _ => return json!({ "kind": "infer" }),
+    };
+    match kind.as_str() {
+        "resolved_path" => path(t),
+        "dyn_trait" => {
+            let traits = list(take(&mut t, &["traits"]), |mut poly| {
+                Some(json!({
+                    "trait": path(take(&mut poly, &["trait"])),
+                    "generic_params": list(take(&mut poly, &["generic_params"]), |p| Some(param(p))),
+                }))
+            });
+            json!({
+                "kind": "dyn_trait",
+                "inner": { "traits": traits, "lifetime": take(&mut t, &["lifetime"]) }
+            })
+        }
+        "primitive" if t == "never" => json!({ "kind": "never" }),
+        "generic" | "primitive" => json!({ "kind": kind, "inner": t }),
+        "function_pointer" => {
+            let mut header = take(&mut t, &["header"]);
+            let abi = match take(&mut header, &["abi"]) {
+                Value::Null => take(&mut t, &["abi"]),
+                abi => abi,
+            };
+            json!({
+                "kind": "function_pointer",
+                "inner": {
+                    "decl": decl(take(&mut t, &["sig", "decl"])),
+                    "generic_params": list(take(&mut t, &["generic_params"]), |p| Some(param(p))),
+                    "header": qualifiers(header),
+                    "abi": abi_string(abi),
+                }
+            })
+        }
+        "tuple" => json!({ "kind": "tuple", "inner": list(t, |t| Some(ty(t))) }),
+        "slice" => json!({ "kind": "slice", "inner": ty(t) }),
+        "array" => json!({
+            "kind": "array",
+            "inner": {
+                "type": ty(take(&mut t, &["type"])),
+                "len": string(take(&mut t, &["len"])),
+            }
+        }),
+        "pat" => ty(take(&mut t, &["type"])),
+        "impl_trait" => json!({ "kind": "impl_trait", "inner": bounds(t) }),
+        "raw_pointer" => json!({
+            "kind": "raw_pointer",
+            "inner": {
+                "mutable": boolean(&mut t, &["is_mutable", "mutable"]),
+                "type": ty(take(&mut t, &["type"])),
+            }
+        }),
+        "borrowed_ref" => json!({
+            "kind": "borrowed_ref",
+            "inner": {
+                "lifetime": take(&mut t, &["lifetime"]),
+                "mutable": boolean(&mut t, &["is_mutable", "mutable"]),
+                "type": ty(take(&mut t, &["type"])),
+            }
+        }),
+        "qualified_path" => {
+            let trait_ = match take(&mut t, &["trait"]) {
+                // inherent associated types
+                Value::Null => json!({ "path": "", "id": "" }),
+                trait_ => trait_,
+            };
+            json!({
+                "kind": "qualified_path",
+                "inner": {
+                    "name": take(&mut t, &["name"]),
+                    "self_type": ty(take(&mut t, &["self_type"])),
+                    "trait": path(trait_),
+                }
+            })
+        }
+        _ => json!({ "kind": "infer" }),
+    }
+}
+
+fn generic_args(args: Value) -> Value {
+    let (kind, mut args) = match args {
+        Value::Object(args) if args.len() == 1 => args.into_iter().next().unwrap(),
+        // return type notation (`T::method(..)`)
+        _ => return json!({ "angle_bracketed": { "args": [], "bindings": [] } }),
+    };
+    match kind.as_str() {
+        "parenthesized" => json!({
+            "parenthesized": {
+                "inputs": list(take(&mut args, &["inputs"]), |t| Some(ty(t))),
+                "output": opt(take(&mut args, &["output"]), ty),
+            }
+        }),
+        _ => json!({
+            "angle_bracketed": {
+                "args": list(take(&mut args, &["args"]), generic_arg),
+                "bindings": list(take(&mut args, &["constraints", "bindings"]), binding),
+            }
+        }),
+    }
+}
+
+fn generic_arg(arg: Value) -> Option<Value> {
+    let (kind, arg) = match arg {
+        Value::Object(arg) => arg.into_iter().next()?,
+        _ => return Some(json!({ "type": { "kind": "infer" } })),
+    };
+    let arg = match kind.as_str() {
+        "lifetime" => json!({ "lifetime": arg }),
+        "type" => json!({ "type": ty(arg) }),
+        "const" => json!({ "const": constant(json!({ "kind": "infer" }), arg) }),
+        _ => json!({ "type": { "kind": "infer" } }),
+    };
+    Some(arg)
+}
+
+fn binding(mut b: Value) -> Option<Value> {
+    let (kind, binding) = match take(&mut b, &["binding"]) {
+        Value::Object(binding) => binding.into_iter().next()?,
+        _ => return None,
+    };
+    let binding = match kind.as_str() {
+        "equality" => json!({ "equality": term(binding) }),
+        _ => json!({ "constraint": bounds(binding) }),
+    };
+    Some(json!({ "name": take(&mut b, &["name"]), "binding": binding }))
+}
+
+/// Converts a `Term` into a type, as the current format only allows types in equalities.
+fn term(t: Value) -> Value {
+    match t {
+        Value::Object(mut t) if t.len() == 1 && t.contains_key("type") => {
+            ty(t.remove("type").unwrap())
+        }
+        Value::Object(mut t) if t.len() == 1 && t.contains_key("constant") => {
+            let mut c = t.remove("constant").unwrap();
+            json!({ "kind": "primitive", "inner": string(take(&mut c, &["expr"])) })
+        }
+        t => ty(t),
+    }
+}
+
+fn constant(type_: Value, mut c: Value) -> Value {
+    json!({
+        "type": type_,
+        "expr": string(take(&mut c, &["expr"])),
+        "value": take(&mut c, &["value"]),
+        "is_literal": boolean(&mut c, &["is_literal"]),
+    })
+}
+
+fn attr(attr: Value) -> String {
+    let (name, args) = match attr {
+        Value::String(attr) if attr.starts_with('#') => return attr,
+        Value::String(attr) => return format!("#[{}]", attr),
+        Value::Object(attr) => match attr.into_iter().next() {
+            Some(attr) => attr,
+            None => return String::new(),
+        },
+        attr => return attr.to_string(),
+    };
+    match (name.as_str(), args) {
+        ("other", Value::String(other)) => other,
+        ("must_use", args) => match args.get("reason").and_then(Value::as_str) {
+            Some(reason) => format!("#[must_use = {:?}]", reason),
+            None => "#[must_use]".into(),
+        },
+        ("repr", args) => {
+            let mut reprs = Vec::new();
+            match args.get("kind").and_then(Value::as_str) {
+                Some("c") => reprs.push("C".to_string()),
+                Some("transparent") => reprs.push("transparent".into()),
+                Some("simd") => reprs.push("simd".into()),
+                _ => {}
+            }
+            if let Some(int) = args.get("int").and_then(Value::as_str) {
+                reprs.push(int.into());
+            }
+            match args.get("packed").and_then(Value::as_u64) {
+                Some(1) => reprs.push("packed".into()),
+                Some(n) => reprs.push(format!("packed({})", n)),
+                None => {}
+            }
+            if let Some(n) = args.get("align").and_then(Value::as_u64) {
+                reprs.push(format!("align({})", n));
+            }
+            if reprs.is_empty() {
+                reprs.push("Rust".into());
+            }
+            format!("#[repr({})]", reprs.join(", "))
+        }
+        ("target_feature", args) => {
+            let features: Vec<&str> = args
+                .get("enable")
+                .and_then(Value::as_array)
+                .map(|f| f.iter().filter_map(Value::as_str).collect())
+                .unwrap_or_default();
+            format!("#[target_feature(enable = {:?})]", features.join(","))
+        }
+        (name, Value::String(value)) => format!("#[{} = {:?}]", name, value),
+        (name, _) => format!("#[{}]", name),
+    }
+}
+
+fn item_kind(kind: &str) -> Option<&'static str> {
+    let kind = match kind {
+        "module" => "module",
+        "extern_crate" => "extern_crate",
+        "use" | "import" => "import",
+        "struct" => "struct",
+        "struct_field" => "struct_field",
+        "union" => "union",
+        "enum" => "enum",
+        "variant" => "variant",
+        "function" | "method" => "function",
+        "type_alias" | "typedef" => "typedef",
+        "opaque_ty" => "opaque_ty",
+        "constant" => "constant",
+        "trait" => "trait",
+        "trait_alias" => "trait_alias",
+        "impl" => "impl",
+        "static" => "static",
+        "extern_type" | "foreign_type" => "foreign_type",
+        "macro" => "macro",
+        "proc_attribute" => "proc_attribute",
+        "proc_derive" => "proc_derive",
+        "assoc_const" => "assoc_const",
+        "assoc_type" => "assoc_type",
+        "primitive" => "primitive",
+        "keyword" => "keyword",
+        _ => return None,
+    };
+    Some(kind)
+}
+
+/// Newer formats use integer ids, the current one uses strings.
+fn id(id: Value) -> Value {
+    match id {
+        Value::Number(n) => Value::String(n.to_string()),
+        id => id,
+    }
+}
+
+fn ids(ids: Value) -> Value {
+    list(ids, |i| Some(id(i)))
+}
+
+/// Takes the first of `keys` present in `v`, which allows for renamed fields.
+fn take(v: &mut Value, keys: &[&str]) -> Value {
+    keys.iter()
+        .find_map(|k| v.get_mut(*k).map(Value::take))
+        .unwrap_or(Value::Null)
+}
+
+fn boolean(v: &mut Value, keys: &[&str]) -> Value {
+    Value::Bool(take(v, keys).as_bool().unwrap_or(false))
+}
+
+fn string(v: Value) -> Value {
+    match v {
+        Value::String(s) => Value::String(s),
+        Value::Null => Value::String(String::new()),
+        v => Value::String(v.to_string()),
+    }
+}
+
+fn opt(v: Value, f: impl FnOnce(Value) -> Value) -> Value {
+    match v {
+        Value::Null => Value::Null,
+        v => f(v),
+    }
+}
+
+fn list(v: Value, f: impl FnMut(Value) -> Option<Value>) -> Value {
+    match v {
+        Value::Array(v) => Value::Array(v.into_iter().filter_map(f).collect()),
+        _ => json!([]),
+    }
+}
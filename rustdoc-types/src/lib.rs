@@ -1,15 +1,134 @@
 //! This is my crate.
 
-use std::rc::Rc;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::{fmt, io};
 
 use druid::{Data, Lens};
 use im::{HashMap, Vector};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+mod compat;
+
+/// Serializes the adjacently tagged `$ty` as `$json` in JSON. Binary formats like the index cache
+/// get the externally tagged `$tagged`, as serde can only read struct and unit variants of
+/// adjacently tagged enums when they are written with their field names.
+macro_rules! dual_repr {
+    ($ty:ty, $json:path, $tagged:path) => {
+        impl Serialize for $ty {
+            fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+                if s.is_human_readable() {
+                    <$json>::serialize(self, s)
+                } else {
+                    <$tagged>::serialize(self, s)
+                }
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $ty {
+            fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+                if d.is_human_readable() {
+                    <$json>::deserialize(d)
+                } else {
+                    <$tagged>::deserialize(d)
+                }
+            }
+        }
+    };
+}
+
+/// The version of the JSON format these types describe. JSON from newer versions of rustdoc is
+/// converted to it when loading.
+pub const FORMAT_VERSION: u32 = 5;
+
+/// Parses rustdoc JSON of any supported format version.
+///
+/// JSON of the current format is deserialized in one pass. Other formats don't fit the types,
+/// which fails early, and are parsed again as a tree to upgrade them.
+pub fn parse(s: &str) -> Result<Crate, Error> {
+    match serde_json::from_str::<Crate>(s) {
+        Ok(krate) if krate.format_version == FORMAT_VERSION => Ok(krate),
+        _ => parse_value(serde_json::from_str(s)?),
+    }
+}
+
+/// Like [`parse`], but streams the JSON from `reader`, e.g. while decompressing it.
+///
+/// Only JSON of the current format is deserialized while streaming, other formats are read again
+/// from `reopen` as a tree to upgrade them. Both readers should be buffered.
+pub fn parse_reader<R: io::Read>(
+    reader: R,
+    reopen: impl FnOnce() -> io::Result<R>,
+) -> Result<Crate, Error> {
+    match serde_json::from_reader::<_, Crate>(reader) {
+        Ok(krate) if krate.format_version == FORMAT_VERSION => Ok(krate),
+        // reading again wouldn't help
+        Err(e) if e.is_io() => Err(Error::Io(e.into())),
+        _ => parse_value(serde_json::from_reader(reopen()?)?),
+    }
+}
+
+/// Like [`parse`], but for already parsed JSON.
+pub fn parse_value(krate: Value) -> Result<Crate, Error> {
+    let version = krate
+        .get("format_version")
+        .and_then(Value::as_u64)
+        .ok_or(Error::MissingVersion)? as u32;
+    if version == FORMAT_VERSION {
+        return Ok(Crate::deserialize(krate)?);
+    }
+    if version < compat::MIN_VERSION {
+        return Err(Error::UnsupportedVersion(version));
+    }
+    match Crate::deserialize(compat::upgrade(krate)) {
+        Ok(krate) => Ok(krate),
+        // probably a breaking change the upgrade doesn't know about yet
+        Err(_) if version > compat::MAX_VERSION => Err(Error::UnsupportedVersion(version)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Json(serde_json::Error),
+    MissingVersion,
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "failed to read rustdoc JSON: {}", e),
+            Error::Json(e) => write!(f, "invalid rustdoc JSON: {}", e),
+            Error::MissingVersion => write!(f, "not rustdoc JSON: `format_version` is missing"),
+            Error::UnsupportedVersion(v) => write!(
+                f,
+                "rustdoc JSON format version {} is not supported (supported are {} and {} to {})",
+                v,
+                FORMAT_VERSION,
+                compat::MIN_VERSION,
+                compat::MAX_VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
 
-pub fn parse(s: &str) -> Crate {
-    serde_json::from_str(s).unwrap()
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
 }
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Json(e)
+    }
+}
+
 /// A `Crate` is the root of the emitted JSON blob. It contains all type/documentation information
 /// about the language items in the local crate, as well as info about external items to allow
 /// tools to find or link to them.
@@ -80,6 +199,7 @@ pub struct Item {
     pub attrs: Vector<String>,
     pub deprecation: Option<Deprecation>,
     pub kind: ItemKind,
+    #[serde(with = "item_enum")]
     pub inner: ItemEnum,
 }
 
@@ -200,11 +320,12 @@ pub enum ItemKind {
 #[serde(rename_all = "snake_case")]
 pub enum ItemEnum {
     ModuleItem(Module),
+    // before `ExternCrateItem`, whose fields an import also has
+    ImportItem(Import),
     ExternCrateItem {
         name: String,
         rename: Option<String>,
     },
-    ImportItem(Import),
 
     StructItem(Struct),
     UnionItem(Union),
@@ -245,6 +366,68 @@ pub enum ItemEnum {
     },
 }
 
+/// `ItemEnum` is untagged in JSON, the variant is found by trying each in turn. Binary formats like
+/// the index cache tag it instead, which is smaller and reads without buffering the item.
+mod item_enum {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::*;
+
+    pub fn serialize<S: Serializer>(inner: &ItemEnum, s: S) -> Result<S::Ok, S::Error> {
+        if s.is_human_readable() {
+            inner.serialize(s)
+        } else {
+            Tagged::serialize(inner, s)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<ItemEnum, D::Error> {
+        if d.is_human_readable() {
+            ItemEnum::deserialize(d)
+        } else {
+            Tagged::deserialize(d)
+        }
+    }
+
+    /// The variants of `ItemEnum`, externally tagged.
+    #[allow(clippy::large_enum_variant, clippy::enum_variant_names)]
+    #[derive(Serialize, Deserialize)]
+    #[serde(remote = "ItemEnum")]
+    enum Tagged {
+        ModuleItem(Module),
+        ImportItem(Import),
+        ExternCrateItem {
+            name: String,
+            rename: Option<String>,
+        },
+        StructItem(Struct),
+        UnionItem(Union),
+        StructFieldItem(Type),
+        EnumItem(Enum),
+        VariantItem(Variant),
+        FunctionItem(Function),
+        TraitItem(Trait),
+        TraitAliasItem(TraitAlias),
+        MethodItem(Method),
+        ImplItem(Impl),
+        TypedefItem(Typedef),
+        OpaqueTyItem(OpaqueTy),
+        ConstantItem(Constant),
+        StaticItem(Static),
+        ForeignTypeItem,
+        MacroItem(String),
+        ProcMacroItem(ProcMacro),
+        AssocConstItem {
+            type_: Type,
+            default: Option<String>,
+        },
+        AssocTypeItem {
+            bounds: Vector<GenericBound>,
+            default: Option<Type>,
+        },
+    }
+}
+
 impl ItemEnum {
     pub fn as_mod(&self) -> Option<&Module> {
 	match self {
@@ -310,15 +493,15 @@ pub struct Enum {
     pub impls: Vector<Id>,
 }
 
-#[derive(Clone, Debug, Data, Serialize, Deserialize, PartialEq)]
-#[serde(rename_all = "snake_case")]
-#[serde(tag = "variant_kind", content = "variant_inner")]
+#[derive(Clone, Debug, Data, PartialEq)]
 pub enum Variant {
     Plain,
     Tuple(Vector<Type>),
     Struct(Vector<Id>),
 }
 
+dual_repr!(Variant, repr::VariantJson, repr::VariantTagged);
+
 #[derive(Clone, Debug, Data, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum StructType {
@@ -384,6 +567,9 @@ pub enum WherePredicate {
     BoundPredicate {
         ty: Type,
         bounds: Vector<GenericBound>,
+        /// Used for HRTBs, `for<'a> T: Trait<'a>`
+        #[serde(default)]
+        generic_params: Vector<GenericParamDef>,
     },
     RegionPredicate {
         lifetime: String,
@@ -416,33 +602,29 @@ pub enum TraitBoundModifier {
     MaybeConst,
 }
 
-#[derive(Clone, Debug, Data, Serialize, Deserialize, PartialEq)]
-#[serde(rename_all = "snake_case")]
-#[serde(tag = "kind", content = "inner")]
+#[derive(Clone, Debug, Data, PartialEq)]
 pub enum Type {
     /// Structs, enums, and traits
     ResolvedPath {
         name: String,
         id: Id,
-        args: Option<Rc<GenericArgs>>,
+        args: Option<Arc<GenericArgs>>,
         param_names: Vector<GenericBound>,
     },
+    /// `dyn for<'a> Fn(&'a str) + Send + 'static`
+    DynTrait(DynTrait),
     /// Parameterized types
     Generic(String),
     /// Fixed-size numeric types (plus int/usize/float), char, arrays, slices, and tuples
     Primitive(String),
     /// `extern "ABI" fn`
-    FunctionPointer(Rc<FunctionPointer>),
+    FunctionPointer(Arc<FunctionPointer>),
     /// `(String, u32, Rc<usize>)`
     Tuple(Vector<Type>),
     /// `[u32]`
-    Slice(Rc<Type>),
+    Slice(Arc<Type>),
     /// [u32; 15]
-    Array {
-        #[serde(rename = "type")]
-        type_: Rc<Type>,
-        len: String,
-    },
+    Array { type_: Arc<Type>, len: String },
     /// `impl TraitA + TraitB + ...`
     ImplTrait(Vector<GenericBound>),
     /// `!`
@@ -450,27 +632,143 @@ pub enum Type {
     /// `_`
     Infer,
     /// `*mut u32`, `*u8`, etc.
-    RawPointer {
-        mutable: bool,
-        #[serde(rename = "type")]
-        type_: Rc<Type>,
-    },
+    RawPointer { mutable: bool, type_: Arc<Type> },
     /// `&'a mut String`, `&str`, etc.
     BorrowedRef {
         lifetime: Option<String>,
         mutable: bool,
-        #[serde(rename = "type")]
-        type_: Rc<Type>,
+        type_: Arc<Type>,
     },
     /// `<Type as Trait>::Name` or associated types like `T::Item` where `T: Iterator`
     QualifiedPath {
         name: String,
-        self_type: Rc<Type>,
-        #[serde(rename = "trait")]
-        trait_: Rc<Type>,
+        self_type: Arc<Type>,
+        trait_: Arc<Type>,
     },
 }
 
+dual_repr!(Type, repr::TypeJson, repr::TypeTagged);
+
+/// The variants of the adjacently tagged enums, for each way they are serialized.
+mod repr {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(remote = "Variant", rename_all = "snake_case")]
+    #[serde(tag = "variant_kind", content = "variant_inner")]
+    pub enum VariantJson {
+        Plain,
+        Tuple(Vector<Type>),
+        Struct(Vector<Id>),
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(remote = "Variant")]
+    pub enum VariantTagged {
+        Plain,
+        Tuple(Vector<Type>),
+        Struct(Vector<Id>),
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(remote = "Type", rename_all = "snake_case")]
+    #[serde(tag = "kind", content = "inner")]
+    pub enum TypeJson {
+        ResolvedPath {
+            name: String,
+            id: Id,
+            args: Option<Arc<GenericArgs>>,
+            param_names: Vector<GenericBound>,
+        },
+        DynTrait(DynTrait),
+        Generic(String),
+        Primitive(String),
+        FunctionPointer(Arc<FunctionPointer>),
+        Tuple(Vector<Type>),
+        Slice(Arc<Type>),
+        Array {
+            #[serde(rename = "type")]
+            type_: Arc<Type>,
+            len: String,
+        },
+        ImplTrait(Vector<GenericBound>),
+        Never,
+        Infer,
+        RawPointer {
+            mutable: bool,
+            #[serde(rename = "type")]
+            type_: Arc<Type>,
+        },
+        BorrowedRef {
+            lifetime: Option<String>,
+            mutable: bool,
+            #[serde(rename = "type")]
+            type_: Arc<Type>,
+        },
+        QualifiedPath {
+            name: String,
+            self_type: Arc<Type>,
+            #[serde(rename = "trait")]
+            trait_: Arc<Type>,
+        },
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(remote = "Type")]
+    pub enum TypeTagged {
+        ResolvedPath {
+            name: String,
+            id: Id,
+            args: Option<Arc<GenericArgs>>,
+            param_names: Vector<GenericBound>,
+        },
+        DynTrait(DynTrait),
+        Generic(String),
+        Primitive(String),
+        FunctionPointer(Arc<FunctionPointer>),
+        Tuple(Vector<Type>),
+        Slice(Arc<Type>),
+        Array {
+            type_: Arc<Type>,
+            len: String,
+        },
+        ImplTrait(Vector<GenericBound>),
+        Never,
+        Infer,
+        RawPointer {
+            mutable: bool,
+            type_: Arc<Type>,
+        },
+        BorrowedRef {
+            lifetime: Option<String>,
+            mutable: bool,
+            type_: Arc<Type>,
+        },
+        QualifiedPath {
+            name: String,
+            self_type: Arc<Type>,
+            trait_: Arc<Type>,
+        },
+    }
+}
+
+#[derive(Clone, Debug, Data, Lens, Serialize, Deserialize, PartialEq)]
+pub struct DynTrait {
+    /// The principal trait followed by any auto traits.
+    pub traits: Vector<PolyTrait>,
+    /// The lifetime bound of the trait object, e.g. `'static` in `dyn Trait + 'static`.
+    pub lifetime: Option<String>,
+}
+
+#[derive(Clone, Debug, Data, Lens, Serialize, Deserialize, PartialEq)]
+pub struct PolyTrait {
+    #[serde(rename = "trait")]
+    pub trait_: Type,
+    /// Used for HRTBs
+    pub generic_params: Vector<GenericParamDef>,
+}
 
 #[derive(Clone, Debug, Data, Lens, Serialize, Deserialize, PartialEq)]
 pub struct FunctionPointer {
@@ -0,0 +1,38 @@
+//! Writing crates to the binary format of the index cache and back, and to JSON and back.
+
+use rustdoc_types::{parse, Crate};
+
+fn fixture(name: &str) -> Crate {
+    let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+    parse(&std::fs::read_to_string(&path).unwrap()).unwrap()
+}
+
+const FIXTURES: [&str; 3] = ["format_28.json", "format_40.json", "format_57.json"];
+
+#[test]
+fn positional_roundtrip() {
+    for name in FIXTURES {
+        let krate = fixture(name);
+        // without field names, like the cache writes them
+        let bytes = rmp_serde::to_vec(&krate).unwrap();
+        let back: Crate =
+            rmp_serde::from_slice(&bytes).unwrap_or_else(|e| panic!("{}: {}", name, e));
+        assert_eq!(back, krate, "{}", name);
+    }
+}
+
+#[test]
+fn json_roundtrip() {
+    for name in FIXTURES {
+        let krate = fixture(name);
+        let json = serde_json::to_string(&krate).unwrap();
+        // still tagged the way rustdoc writes it
+        assert!(
+            json.contains(r#""kind":"resolved_path","inner":{"#),
+            "{}",
+            name
+        );
+        let back: Crate = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, krate, "{}", name);
+    }
+}
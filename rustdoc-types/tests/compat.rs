@@ -0,0 +1,272 @@
+//! Upgrading JSON of newer format versions, from small crates written by hand in the shape of
+//! each version.
+
+use std::io::Cursor;
+
+use rustdoc_types::{
+    parse, parse_reader, Crate, Error, GenericArg, GenericArgs, GenericBound, GenericParamDefKind,
+    Id, Item, ItemEnum, ItemKind, Qualifiers, StructType, Type, Variant, Visibility,
+    WherePredicate, FORMAT_VERSION,
+};
+
+fn read_fixture(name: &str) -> String {
+    let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+    std::fs::read_to_string(&path).unwrap()
+}
+
+fn fixture(name: &str) -> Crate {
+    parse(&read_fixture(name)).unwrap_or_else(|e| panic!("{}: {}", name, e))
+}
+
+fn id(id: &str) -> Id {
+    Id(id.into())
+}
+
+fn item<'a>(krate: &'a Crate, id: &str) -> &'a Item {
+    &krate.index[&Id(id.into())]
+}
+
+fn resolved_path(ty: &Type) -> (&str, &Id) {
+    match ty {
+        Type::ResolvedPath { name, id, .. } => (name, id),
+        ty => panic!("not a path: {:?}", ty),
+    }
+}
+
+#[test]
+fn format_28() {
+    let krate = fixture("format_28.json");
+    assert_eq!(krate.root, id("0:0"));
+    assert_eq!(krate.crate_version.as_deref(), Some("0.1.0"));
+
+    // the primitive has no counterpart and is dropped, also from its module
+    assert_eq!(krate.index.len(), 4);
+    let root = item(&krate, "0:0");
+    assert_eq!(root.kind, ItemKind::Module);
+    match &root.inner {
+        ItemEnum::ModuleItem(m) => {
+            assert!(m.is_crate);
+            assert_eq!(m.items, vec![id("0:1"), id("0:2")].into());
+        }
+        inner => panic!("not a module: {:?}", inner),
+    }
+
+    let add = item(&krate, "0:1");
+    assert_eq!(add.attrs, vec!["#[must_use]".to_string()].into());
+    assert_eq!(add.links.get("`Point`"), Some(&id("0:2")));
+    match &add.inner {
+        ItemEnum::FunctionItem(f) => {
+            assert_eq!(f.header, vec![Qualifiers::Unsafe].into());
+            assert_eq!(f.abi, "\"Rust\"");
+            let (name, ty) = &f.decl.inputs[0];
+            assert_eq!(name, "p");
+            match ty {
+                Type::BorrowedRef { mutable, type_, .. } => {
+                    assert!(*mutable);
+                    assert_eq!(resolved_path(type_), ("Point", &id("0:2")));
+                }
+                ty => panic!("not a reference: {:?}", ty),
+            }
+            assert_eq!(f.decl.output, Some(Type::Primitive("i32".into())));
+        }
+        inner => panic!("not a function: {:?}", inner),
+    }
+
+    let point = item(&krate, "0:2");
+    assert_eq!(
+        point.deprecation.as_ref().and_then(|d| d.since.as_deref()),
+        Some("0.1.0")
+    );
+    match &point.inner {
+        ItemEnum::StructItem(s) => {
+            assert_eq!(s.struct_type, StructType::Plain);
+            assert_eq!(s.fields, vec![id("0:3")].into());
+            assert!(s.fields_stripped);
+        }
+        inner => panic!("not a struct: {:?}", inner),
+    }
+    assert_eq!(
+        item(&krate, "0:3").inner,
+        ItemEnum::StructFieldItem(Type::Primitive("i32".into()))
+    );
+    assert_eq!(krate.paths[&id("0:4")].kind, ItemKind::Primitive);
+}
+
+#[test]
+fn format_40() {
+    let krate = fixture("format_40.json");
+    // integer ids become strings
+    assert_eq!(krate.root, id("0"));
+    assert!(krate.includes_private);
+    assert_eq!(krate.external_crates[&1].name, "core");
+
+    let copied = item(&krate, "1");
+    assert_eq!(copied.visibility, Visibility::Crate);
+    match &copied.inner {
+        ItemEnum::FunctionItem(f) => {
+            assert_eq!(f.header, vec![Qualifiers::Const].into());
+            assert_eq!(f.abi, "\"C\"");
+            assert_eq!(
+                f.decl.inputs,
+                vec![("value".to_string(), Type::Generic("T".into()))].into()
+            );
+            assert_eq!(f.decl.output, None);
+
+            let param = &f.generics.params[0];
+            assert_eq!(param.name, "T");
+            match &param.kind {
+                GenericParamDefKind::Type { bounds, .. } => match &bounds[0] {
+                    GenericBound::TraitBound { trait_, .. } => {
+                        assert_eq!(resolved_path(trait_), ("Copy", &id("9")))
+                    }
+                    bound => panic!("not a trait bound: {:?}", bound),
+                },
+                kind => panic!("not a type parameter: {:?}", kind),
+            }
+            match &f.generics.where_predicates[0] {
+                WherePredicate::BoundPredicate { ty, bounds, .. } => {
+                    assert_eq!(*ty, Type::Generic("T".into()));
+                    assert_eq!(
+                        bounds,
+                        &vec![GenericBound::Outlives("'static".into())].into()
+                    );
+                }
+                predicate => panic!("not a bound predicate: {:?}", predicate),
+            }
+        }
+        inner => panic!("not a function: {:?}", inner),
+    }
+
+    // tuple structs list stripped fields as `null`
+    match &item(&krate, "2").inner {
+        ItemEnum::StructItem(s) => {
+            assert_eq!(s.struct_type, StructType::Tuple);
+            assert_eq!(s.fields, vec![id("3")].into());
+            assert!(s.fields_stripped);
+        }
+        inner => panic!("not a struct: {:?}", inner),
+    }
+
+    match &item(&krate, "4").inner {
+        ItemEnum::ImportItem(i) => {
+            assert_eq!(i.span, "std::fmt");
+            assert_eq!(i.name, "fmt");
+            assert_eq!(i.id, Some(id("9")));
+            assert!(!i.glob);
+        }
+        inner => panic!("not an import: {:?}", inner),
+    }
+}
+
+#[test]
+fn format_57() {
+    let krate = fixture("format_57.json");
+
+    let shape = item(&krate, "1");
+    let attrs: Vec<_> = shape.attrs.iter().map(String::as_str).collect();
+    assert_eq!(
+        attrs,
+        ["#[repr(C, u8)]", "#[must_use = \"pure\"]", "#[doc(hidden)]"]
+    );
+    match &shape.inner {
+        ItemEnum::EnumItem(e) => {
+            assert_eq!(e.variants, vec![id("2"), id("4")].into());
+            assert!(!e.variants_stripped);
+        }
+        inner => panic!("not an enum: {:?}", inner),
+    }
+    // tuple variants get the types of their fields inline
+    assert_eq!(
+        item(&krate, "2").inner,
+        ItemEnum::VariantItem(Variant::Tuple(vec![Type::Primitive("f32".into())].into()))
+    );
+    assert_eq!(
+        item(&krate, "4").inner,
+        ItemEnum::VariantItem(Variant::Plain)
+    );
+
+    let shapes = item(&krate, "3");
+    assert_eq!(shapes.kind, ItemKind::Typedef);
+    assert_eq!(krate.paths[&id("3")].kind, ItemKind::Typedef);
+    match &shapes.inner {
+        ItemEnum::TypedefItem(t) => {
+            assert_eq!(resolved_path(&t.type_), ("Vec", &id("8")));
+            let args = match &t.type_ {
+                Type::ResolvedPath { args, .. } => args.as_deref(),
+                _ => None,
+            };
+            match args {
+                Some(GenericArgs::AngleBracketed { args, bindings }) => {
+                    assert!(bindings.is_empty());
+                    match &args[0] {
+                        GenericArg::Type(ty) => assert_eq!(resolved_path(ty), ("Shape", &id("1"))),
+                        arg => panic!("not a type: {:?}", arg),
+                    }
+                }
+                args => panic!("not angle bracketed: {:?}", args),
+            }
+        }
+        inner => panic!("not a type alias: {:?}", inner),
+    }
+
+    match &item(&krate, "5").inner {
+        ItemEnum::FunctionItem(f) => {
+            assert_eq!(f.header, vec![Qualifiers::Async].into());
+            match &f.decl.inputs[0].1 {
+                Type::BorrowedRef { mutable, type_, .. } => {
+                    assert!(!*mutable);
+                    assert_eq!(resolved_path(type_), ("Shape", &id("1")));
+                }
+                ty => panic!("not a reference: {:?}", ty),
+            }
+        }
+        inner => panic!("not a function: {:?}", inner),
+    }
+}
+
+#[test]
+fn current_format() {
+    // the empty crate is kept in the current format, for the tests of the app too
+    let json = read_fixture("empty.json");
+    let krate = parse(&json).unwrap();
+    assert_eq!(krate.root, id("0:0"));
+    assert_eq!(krate.format_version, FORMAT_VERSION);
+
+    // errors in the current format are reported as they are
+    let broken = json.replace(r#""0:0""#, "0");
+    assert!(matches!(parse(&broken), Err(Error::Json(_))));
+}
+
+#[test]
+fn unsupported_versions() {
+    let old = r#"{ "root": "0:0", "index": {}, "format_version": 10 }"#;
+    assert!(matches!(parse(old), Err(Error::UnsupportedVersion(10))));
+
+    // newer versions are tried, and rejected when they don't fit
+    let new = r#"{ "index": {}, "paths": {}, "format_version": 99 }"#;
+    assert!(matches!(parse(new), Err(Error::UnsupportedVersion(99))));
+
+    let missing = r#"{ "root": "0:0", "index": {} }"#;
+    assert!(matches!(parse(missing), Err(Error::MissingVersion)));
+}
+
+#[test]
+fn streaming() {
+    // older formats are read again to upgrade them
+    let json = read_fixture("format_28.json");
+    let mut reopened = false;
+    let krate = parse_reader(Cursor::new(json.as_bytes()), || {
+        reopened = true;
+        Ok(Cursor::new(json.as_bytes()))
+    })
+    .unwrap();
+    assert!(reopened);
+    assert_eq!(krate, fixture("format_28.json"));
+
+    let json = read_fixture("empty.json");
+    let krate = parse_reader(Cursor::new(json.as_bytes()), || -> std::io::Result<_> {
+        panic!("the current format is read once")
+    })
+    .unwrap();
+    assert_eq!(krate.format_version, FORMAT_VERSION);
+}
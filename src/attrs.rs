@@ -0,0 +1,100 @@
+/// Names of the shown attributes, in the order the filter cycles through them.
+pub const SHOWN: &[&str] = &[
+    "must_use",
+    "non_exhaustive",
+    "repr",
+    "track_caller",
+    "doc(cfg)",
+];
+
+/// `attr` in source form if it is one of [`SHOWN`].
+pub fn relevant(attr: &str) -> Option<String> {
+    // newer compilers print some attributes in their parsed form
+    let attr = match attr {
+        "#[attr = TrackCaller]" => "#[track_caller]",
+        _ if attr.starts_with("#[attr = NonExhaustive") => "#[non_exhaustive]",
+        _ if attr.starts_with("#[attr = MustUse") => "#[must_use]",
+        _ => attr,
+    };
+    name(attr).map(|_| attr.to_string())
+}
+
+/// The name of `attr` as in [`SHOWN`], `#[repr(C)]` is named `repr`.
+pub fn name(attr: &str) -> Option<&'static str> {
+    let inner = attr.strip_prefix("#[")?;
+    if inner.starts_with("doc(cfg(") {
+        return Some("doc(cfg)");
+    }
+    let end = inner
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(inner.len());
+    SHOWN.iter().copied().find(|name| *name == &inner[..end])
+}
+
+/// The short form of `attr` shown as a badge, `#[repr(C)]` becomes `repr(C)`.
+pub fn badge(attr: &str) -> String {
+    let inner = attr
+        .strip_prefix("#[")
+        .and_then(|a| a.strip_suffix(']'))
+        .unwrap_or(attr);
+    match name(attr) {
+        Some("must_use") => "must_use".into(),
+        Some("doc(cfg)") => inner
+            .strip_prefix("doc(")
+            .and_then(|a| a.strip_suffix(')'))
+            .unwrap_or(inner)
+            .into(),
+        _ => inner.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relevant_attributes() {
+        assert_eq!(
+            relevant("#[repr(C, u8)]").as_deref(),
+            Some("#[repr(C, u8)]")
+        );
+        assert_eq!(
+            relevant("#[must_use = \"pure\"]").as_deref(),
+            Some("#[must_use = \"pure\"]")
+        );
+        assert_eq!(relevant("#[inline]"), None);
+        assert_eq!(relevant("#[doc(hidden)]"), None);
+        assert_eq!(relevant("#[reprx]"), None);
+        // the parsed form of newer compilers
+        assert_eq!(
+            relevant("#[attr = TrackCaller]").as_deref(),
+            Some("#[track_caller]")
+        );
+        assert_eq!(
+            relevant("#[attr = NonExhaustive]").as_deref(),
+            Some("#[non_exhaustive]")
+        );
+        assert_eq!(
+            relevant("#[attr = MustUse {reason: \"pure\"}]").as_deref(),
+            Some("#[must_use]")
+        );
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(name("#[repr(transparent)]"), Some("repr"));
+        assert_eq!(name("#[must_use = \"pure\"]"), Some("must_use"));
+        assert_eq!(name("#[non_exhaustive]"), Some("non_exhaustive"));
+        assert_eq!(name("#[doc(cfg(feature = \"std\"))]"), Some("doc(cfg)"));
+        assert_eq!(name("#[doc(alias = \"x\")]"), None);
+        assert_eq!(name("repr(C)"), None);
+    }
+
+    #[test]
+    fn badges() {
+        assert_eq!(badge("#[repr(C)]"), "repr(C)");
+        assert_eq!(badge("#[must_use = \"pure\"]"), "must_use");
+        assert_eq!(badge("#[doc(cfg(unix))]"), "cfg(unix)");
+        assert_eq!(badge("#[non_exhaustive]"), "non_exhaustive");
+    }
+}
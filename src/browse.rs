@@ -0,0 +1,194 @@
+use std::collections::HashSet;
+
+use druid::im::Vector;
+use rustdoc_types as rdoc;
+
+use crate::delegate::{deprecated_id, opens, Docs};
+use crate::{attrs, data};
+
+/// Something done while browsing a crate, the same in the window and the terminal UI.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    Goto(rdoc::Id),
+    Back,
+    /// Shows the index of deprecated items.
+    ShowDeprecated,
+    TogglePrivate,
+    ToggleFullPaths,
+    /// Lists only items with the next attribute of [`attrs::SHOWN`].
+    CycleAttrFilter,
+    ToggleDeprecated,
+    ToggleFeatureFilter,
+    /// Enables or disables a crate feature in the feature filter.
+    ToggleFeature(String),
+}
+
+/// What the toggles make pages show, kept by each frontend and copied for the thread that loads
+/// a crate.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub show_private: bool,
+    /// Only list items with this attribute, one of [`attrs::SHOWN`].
+    pub attr_filter: Option<String>,
+    pub hide_deprecated: bool,
+    pub full_paths: bool,
+    /// The crate features seen in `#[doc(cfg(..))]` and whether each is enabled.
+    pub features: Vector<data::Feature>,
+    /// Only list items available with the enabled features.
+    pub feature_filter: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            show_private: true,
+            attr_filter: None,
+            hide_deprecated: false,
+            full_paths: false,
+            features: Vector::new(),
+            feature_filter: false,
+        }
+    }
+}
+
+impl Settings {
+    pub fn new(data: &data::App) -> Self {
+        Self {
+            show_private: data.show_private,
+            attr_filter: data.attr_filter.clone(),
+            hide_deprecated: data.hide_deprecated,
+            full_paths: data.full_paths,
+            features: data.features.clone(),
+            feature_filter: data.feature_filter,
+        }
+    }
+
+    /// Writes the toggles back to the app, for the window to show them.
+    pub fn store(self, data: &mut data::App) {
+        data.show_private = self.show_private;
+        data.attr_filter = self.attr_filter;
+        data.hide_deprecated = self.hide_deprecated;
+        data.full_paths = self.full_paths;
+        data.features = self.features;
+        data.feature_filter = self.feature_filter;
+    }
+
+    /// Takes the features of the crate in `docs`, features disabled before stay disabled.
+    pub fn load_features(&mut self, docs: &Docs) {
+        self.features = docs.features(&self.features);
+    }
+
+    /// The features the filter lists items for, `None` when it is off.
+    fn enabled_features(&self) -> Option<HashSet<String>> {
+        if !self.feature_filter {
+            return None;
+        }
+        let enabled = self.features.iter().filter(|f| f.enabled);
+        Some(enabled.map(|f| f.name.clone()).collect())
+    }
+}
+
+impl Docs {
+    /// Makes pages show what `settings` asks for, they are rebuilt when that changed.
+    pub fn set_settings(&mut self, settings: &Settings) {
+        self.set_show_private(settings.show_private);
+        self.set_attr_filter(settings.attr_filter.clone());
+        self.set_hide_deprecated(settings.hide_deprecated);
+        self.set_full_paths(settings.full_paths);
+        self.set_features(settings.enabled_features());
+    }
+
+    /// Does `action`, updating the toggles in `settings`. Returns whether the current page
+    /// changed, which it doesn't when going back without an earlier item or going to an id
+    /// without a page.
+    pub fn apply(&mut self, action: Action, settings: &mut Settings) -> bool {
+        match action {
+            Action::Goto(id) if opens(self.krate(), &id) => self.goto(&id),
+            Action::Goto(_) => return false,
+            Action::Back => return self.back(),
+            Action::ShowDeprecated => self.goto(&deprecated_id()),
+            Action::TogglePrivate => settings.show_private = !settings.show_private,
+            Action::ToggleFullPaths => settings.full_paths = !settings.full_paths,
+            Action::CycleAttrFilter => {
+                let next = match &settings.attr_filter {
+                    None => attrs::SHOWN.first(),
+                    Some(current) => attrs::SHOWN
+                        .iter()
+                        .skip_while(|name| **name != current.as_str())
+                        .nth(1),
+                };
+                settings.attr_filter = next.map(|name| name.to_string());
+            }
+            Action::ToggleDeprecated => settings.hide_deprecated = !settings.hide_deprecated,
+            Action::ToggleFeatureFilter => settings.feature_filter = !settings.feature_filter,
+            Action::ToggleFeature(name) => {
+                for feature in settings.features.iter_mut() {
+                    if feature.name == name {
+                        feature.enabled = !feature.enabled;
+                    }
+                }
+            }
+        }
+        self.set_settings(settings);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::delegate::empty_crate;
+
+    fn docs() -> Docs {
+        Docs::new(empty_crate())
+    }
+
+    #[test]
+    fn navigation() {
+        let mut docs = docs();
+        let mut settings = Settings::default();
+        let root = docs.current().clone();
+        assert!(!docs.apply(Action::Back, &mut settings));
+        // ids without a page aren't opened
+        assert!(!docs.apply(Action::Goto(rdoc::Id("0:1".into())), &mut settings));
+        assert_eq!(*docs.current(), root);
+        assert!(docs.apply(Action::ShowDeprecated, &mut settings));
+        assert_eq!(*docs.current(), deprecated_id());
+        assert!(docs.apply(Action::Back, &mut settings));
+        assert_eq!(*docs.current(), root);
+    }
+
+    #[test]
+    fn toggles() {
+        let mut docs = docs();
+        let mut settings = Settings::default();
+        docs.apply(Action::TogglePrivate, &mut settings);
+        assert!(!settings.show_private);
+
+        // the attribute filter goes through every shown attribute, then turns off
+        let mut filters = Vec::new();
+        for _ in 0..=attrs::SHOWN.len() {
+            docs.apply(Action::CycleAttrFilter, &mut settings);
+            filters.push(settings.attr_filter.clone());
+        }
+        let mut expected: Vec<_> = attrs::SHOWN.iter().map(|a| Some(a.to_string())).collect();
+        expected.push(None);
+        assert_eq!(filters, expected);
+
+        settings.features = vec![
+            data::Feature {
+                name: "serde".into(),
+                enabled: true,
+            },
+            data::Feature {
+                name: "std".into(),
+                enabled: true,
+            },
+        ]
+        .into();
+        docs.apply(Action::ToggleFeatureFilter, &mut settings);
+        docs.apply(Action::ToggleFeature("serde".into()), &mut settings);
+        let enabled: HashSet<_> = vec!["std".to_string()].into_iter().collect();
+        assert_eq!(settings.enabled_features(), Some(enabled));
+    }
+}
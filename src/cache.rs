@@ -0,0 +1,86 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use rustdoc_types as rdoc;
+use serde::{Deserialize, Serialize};
+
+/// Bump when the cached representation changes without a new `rdoc::FORMAT_VERSION`, including
+/// the order of fields, which are written without their names.
+const CACHE_VERSION: u32 = 3;
+
+/// Identifies the JSON a cache entry was built from, written before the crate itself.
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+struct Key {
+    cache_version: u32,
+    format_version: u32,
+    path: PathBuf,
+    modified: SystemTime,
+    len: u64,
+}
+
+impl Key {
+    fn new(path: &Path) -> io::Result<Self> {
+        let meta = fs::metadata(path)?;
+        Ok(Key {
+            cache_version: CACHE_VERSION,
+            format_version: rdoc::FORMAT_VERSION,
+            path: path.canonicalize()?,
+            modified: meta.modified()?,
+            len: meta.len(),
+        })
+    }
+}
+
+/// Returns the cached crate for the JSON at `path`, if it is still up to date.
+pub fn load(path: &Path) -> Option<rdoc::Crate> {
+    let key = Key::new(path).ok()?;
+    // decoding from memory is quicker than through a reader
+    let bytes = fs::read(entry(&key.path)?).ok()?;
+    let mut de = rmp_serde::Deserializer::from_read_ref(&bytes);
+    let cached = Key::deserialize(&mut de).ok()?;
+    if cached != key {
+        return None;
+    }
+    match rdoc::Crate::deserialize(&mut de) {
+        Ok(krate) => Some(krate),
+        Err(e) => {
+            log::warn!("ignoring corrupt cache for {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Caches `krate` parsed from the JSON at `path`, replacing any outdated entry.
+pub fn store(path: &Path, krate: &rdoc::Crate) {
+    if let Err(e) = try_store(path, krate) {
+        log::warn!("failed to cache {}: {}", path.display(), e);
+    }
+}
+
+fn try_store(path: &Path, krate: &rdoc::Crate) -> Result<(), Box<dyn std::error::Error>> {
+    let key = Key::new(path)?;
+    let entry = entry(&key.path).ok_or("no cache directory")?;
+    fs::create_dir_all(entry.parent().unwrap())?;
+
+    // write to a temporary file first so that a crash never leaves a half written entry
+    let tmp = entry.with_extension("tmp");
+    let mut writer = BufWriter::new(File::create(&tmp)?);
+    // structs are written as arrays, which is smaller and quicker to read than with field names
+    rmp_serde::encode::write(&mut writer, &key)?;
+    rmp_serde::encode::write(&mut writer, krate)?;
+    writer.flush()?;
+    drop(writer);
+    fs::rename(tmp, entry)?;
+    Ok(())
+}
+
+fn entry(path: &Path) -> Option<PathBuf> {
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    let name = format!("{:016x}.bin", hasher.finish());
+    Some(dirs::cache_dir()?.join("druid-rustdoc").join(name))
+}
@@ -0,0 +1,198 @@
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+
+use druid::{ExtEventSink, Selector, SingleUse, Target};
+use rustdoc_types as rdoc;
+use serde_json::Value;
+
+use crate::browse::Settings;
+use crate::load::{load_blocking, LoadError, LOADED};
+
+/// A line of output from cargo while building the docs, with the generation of the load like
+/// [`LOADED`].
+pub const BUILD_LOG: Selector<(usize, String)> = Selector::new("druid-rustdoc.build-log");
+
+const LIB_KINDS: &[&str] = &["lib", "rlib", "dylib", "cdylib", "staticlib", "proc-macro"];
+
+#[derive(Debug, Default, Clone)]
+pub struct Options {
+    pub package: Option<String>,
+    pub features: Vec<String>,
+    pub all_features: bool,
+    pub no_default_features: bool,
+    pub document_private_items: bool,
+    pub target_dir: Option<PathBuf>,
+}
+
+/// The crate that gets documented.
+struct DocTarget {
+    package: String,
+    name: String,
+    is_lib: bool,
+    target_dir: PathBuf,
+}
+
+/// Returns the manifest if `path` is a cargo project rather than a JSON file.
+pub fn manifest_path(path: &Path) -> Option<PathBuf> {
+    if path.is_dir() {
+        Some(path.join("Cargo.toml")).filter(|p| p.is_file())
+    } else if path.file_name()? == "Cargo.toml" {
+        Some(path.to_path_buf())
+    } else {
+        None
+    }
+}
+
+/// Documents the project at `manifest` with the nightly toolchain on a background thread.
+///
+/// Cargo's output is streamed as [`BUILD_LOG`], the generated JSON is then loaded like any other.
+pub fn build(
+    manifest: PathBuf,
+    opts: Options,
+    cache: bool,
+    settings: Settings,
+    generation: usize,
+    sink: ExtEventSink,
+) {
+    thread::spawn(move || {
+        let mut log = |line: String| {
+            let _ = sink.submit_command(BUILD_LOG, (generation, line), Target::Auto);
+        };
+        match document(&manifest, &opts, &mut log) {
+            Ok(json) => load_blocking(json, cache, settings, generation, sink),
+            Err(e) => {
+                let loaded = (generation, SingleUse::new(Err(e)));
+                let _ = sink.submit_command(LOADED, loaded, Target::Auto);
+            }
+        }
+    });
+}
+
+/// Runs `cargo rustdoc`, returning the path of the generated JSON. Cargo's output is passed to
+/// `log` line by line.
+pub fn document(
+    manifest: &Path,
+    opts: &Options,
+    log: &mut dyn FnMut(String),
+) -> Result<PathBuf, LoadError> {
+    let target = find_target(manifest, opts)?;
+
+    let mut cmd = Command::new("cargo");
+    cmd.args(&["+nightly", "rustdoc", "--color", "never", "--manifest-path"])
+        .arg(manifest)
+        .args(&["--package", &target.package]);
+    if target.is_lib {
+        cmd.arg("--lib");
+    } else {
+        cmd.args(&["--bin", &target.name]);
+    }
+    if !opts.features.is_empty() {
+        cmd.arg("--features").arg(opts.features.join(","));
+    }
+    if opts.all_features {
+        cmd.arg("--all-features");
+    }
+    if opts.no_default_features {
+        cmd.arg("--no-default-features");
+    }
+    if let Some(dir) = &opts.target_dir {
+        cmd.arg("--target-dir").arg(dir);
+    }
+    cmd.args(&["--", "-Z", "unstable-options", "--output-format", "json"]);
+    if opts.document_private_items {
+        cmd.arg("--document-private-items");
+    }
+
+    let mut child = cmd
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| LoadError::Build(format!("failed to run cargo: {}", e)))?;
+    // progress and compiler errors are printed to stderr
+    let stderr = child.stderr.take().unwrap();
+    for line in BufReader::new(stderr).lines() {
+        log(line?);
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(LoadError::Build(format!(
+            "cargo rustdoc failed ({})",
+            status
+        )));
+    }
+
+    let file = format!("{}.json", target.name.replace('-', "_"));
+    Ok(target.target_dir.join("doc").join(file))
+}
+
+/// Picks the package and target to document using `cargo metadata`.
+fn find_target(manifest: &Path, opts: &Options) -> Result<DocTarget, LoadError> {
+    let output = Command::new("cargo")
+        .args(&[
+            "metadata",
+            "--no-deps",
+            "--format-version",
+            "1",
+            "--manifest-path",
+        ])
+        .arg(manifest)
+        .output()
+        .map_err(|e| LoadError::Build(format!("failed to run cargo: {}", e)))?;
+    if !output.status.success() {
+        let err = String::from_utf8_lossy(&output.stderr);
+        return Err(LoadError::Build(err.trim().into()));
+    }
+    let meta: Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| LoadError::Parse(rdoc::Error::Json(e)))?;
+
+    let manifest = manifest.canonicalize()?;
+    let packages = meta["packages"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let package = packages
+        .iter()
+        .find(|p| match &opts.package {
+            Some(name) => p["name"] == name.as_str(),
+            None => p["manifest_path"].as_str().map(Path::new) == Some(&manifest),
+        })
+        .ok_or_else(|| {
+            LoadError::Build(match &opts.package {
+                Some(name) => format!("package `{}` not found", name),
+                None => "no package at this manifest, select one with `--package`".into(),
+            })
+        })?;
+
+    let has_kind = |t: &&Value, kinds: &[&str]| {
+        t["kind"].as_array().map_or(false, |k| {
+            k.iter().any(|k| kinds.iter().any(|kind| k == kind))
+        })
+    };
+    let targets = package["targets"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let (target, is_lib) = match targets.iter().find(|t| has_kind(t, LIB_KINDS)) {
+        Some(t) => (t, true),
+        None => (
+            targets
+                .iter()
+                .find(|t| has_kind(t, &["bin"]))
+                .ok_or_else(|| LoadError::Build("package has no lib or bin target".into()))?,
+            false,
+        ),
+    };
+
+    let target_dir = match &opts.target_dir {
+        Some(dir) => dir.clone(),
+        None => meta["target_directory"].as_str().unwrap_or("target").into(),
+    };
+    Ok(DocTarget {
+        package: package["name"].as_str().unwrap_or_default().into(),
+        name: target["name"].as_str().unwrap_or_default().into(),
+        is_lib,
+        target_dir,
+    })
+}
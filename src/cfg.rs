@@ -0,0 +1,332 @@
+use std::collections::{BTreeSet, HashSet};
+
+/// A parsed `#[doc(cfg(..))]` predicate.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cfg {
+    /// `feature = "name"`
+    Feature(String),
+    /// Any other predicate like `unix` or `target_os = "linux"`, kept in source form.
+    Other(String),
+    All(Vec<Cfg>),
+    Any(Vec<Cfg>),
+    Not(Box<Cfg>),
+}
+
+impl Cfg {
+    /// Combines the `#[doc(cfg(..))]` attributes in `attrs`, if there are any.
+    pub fn from_attrs<'a>(attrs: impl IntoIterator<Item = &'a String>) -> Option<Cfg> {
+        let mut cfgs: Vec<_> = attrs
+            .into_iter()
+            .filter_map(|a| Cfg::parse_attr(a))
+            .collect();
+        match cfgs.len() {
+            0 => None,
+            1 => cfgs.pop(),
+            _ => Some(Cfg::All(cfgs)),
+        }
+    }
+
+    /// Parses `#[doc(cfg(..))]`.
+    pub fn parse_attr(attr: &str) -> Option<Cfg> {
+        let inner = attr.strip_prefix("#[doc(cfg(")?.strip_suffix("))]")?;
+        let mut parser = Parser { s: inner };
+        let cfg = parser.cfg()?;
+        parser.skip_ws();
+        if parser.s.is_empty() {
+            Some(cfg)
+        } else {
+            None
+        }
+    }
+
+    /// Adds the features mentioned to `out`.
+    pub fn features(&self, out: &mut BTreeSet<String>) {
+        match self {
+            Cfg::Feature(f) => {
+                out.insert(f.clone());
+            }
+            Cfg::Other(_) => {}
+            Cfg::All(cfgs) | Cfg::Any(cfgs) => cfgs.iter().for_each(|c| c.features(out)),
+            Cfg::Not(c) => c.features(out),
+        }
+    }
+
+    /// Whether the item is available with `features` enabled. Other predicates can't be known
+    /// here, so only a cfg that is false whatever they are rules the item out.
+    pub fn enabled(&self, features: &HashSet<String>) -> bool {
+        self.eval(features) != Some(false)
+    }
+
+    fn eval(&self, features: &HashSet<String>) -> Option<bool> {
+        let evals = |cfgs: &[Cfg]| cfgs.iter().map(|c| c.eval(features)).collect::<Vec<_>>();
+        match self {
+            Cfg::Feature(f) => Some(features.contains(f)),
+            Cfg::Other(_) => None,
+            // one known `false` decides it, whatever the unknown ones are
+            Cfg::All(cfgs) => match evals(cfgs) {
+                e if e.contains(&Some(false)) => Some(false),
+                e if e.contains(&None) => None,
+                _ => Some(true),
+            },
+            Cfg::Any(cfgs) => match evals(cfgs) {
+                e if e.contains(&Some(true)) => Some(true),
+                e if e.contains(&None) => None,
+                _ => Some(false),
+            },
+            Cfg::Not(c) => c.eval(features).map(|b| !b),
+        }
+    }
+
+    /// Describes the cfg like rustdoc, e.g. "crate feature `std`".
+    pub fn describe(&self) -> String {
+        match self {
+            Cfg::Feature(f) => format!("crate feature `{}`", f),
+            Cfg::Other(o) => format!("`{}`", o),
+            Cfg::All(cfgs) => join(cfgs, " and ", Cfg::describe_nested),
+            Cfg::Any(cfgs) => join(cfgs, " or ", Cfg::describe_nested),
+            Cfg::Not(c) if c.is_group() => format!("not ({})", c.describe()),
+            Cfg::Not(c) => format!("non-{}", c.describe()),
+        }
+    }
+
+    /// Describes an operand of `all` or `any`, nested groups in parentheses like rustdoc.
+    fn describe_nested(&self) -> String {
+        if self.is_group() {
+            format!("({})", self.describe())
+        } else {
+            self.describe()
+        }
+    }
+
+    fn is_group(&self) -> bool {
+        matches!(self, Cfg::All(_) | Cfg::Any(_))
+    }
+
+    /// The note on pages of items with this cfg, in Markdown.
+    pub fn note(&self) -> String {
+        format!("Available on **{}** only.", self.describe())
+    }
+
+    /// A short form for badges, e.g. `std & !alloc`.
+    pub fn short(&self) -> String {
+        self.short_in(0)
+    }
+
+    /// The short form as an operand of an operator binding as tight as `outer`, in parentheses
+    /// when it binds less tight. `!` binds tightest, then `&`, then `|`.
+    fn short_in(&self, outer: u8) -> String {
+        let (short, precedence) = match self {
+            Cfg::Feature(f) => (f.clone(), 3),
+            Cfg::Other(o) => (o.clone(), 3),
+            Cfg::All(cfgs) => (join(cfgs, " & ", |c| c.short_in(2)), 2),
+            Cfg::Any(cfgs) => (join(cfgs, " | ", |c| c.short_in(1)), 1),
+            Cfg::Not(c) => (format!("!{}", c.short_in(3)), 3),
+        };
+        if precedence < outer {
+            format!("({})", short)
+        } else {
+            short
+        }
+    }
+}
+
+fn join(cfgs: &[Cfg], sep: &str, f: fn(&Cfg) -> String) -> String {
+    cfgs.iter().map(f).collect::<Vec<_>>().join(sep)
+}
+
+struct Parser<'a> {
+    s: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    fn cfg(&mut self) -> Option<Cfg> {
+        let name = self.ident()?;
+        if self.eat('=') {
+            let value = self.string()?;
+            return Some(match name {
+                "feature" => Cfg::Feature(value.into()),
+                _ => Cfg::Other(format!("{} = \"{}\"", name, value)),
+            });
+        }
+        if !self.eat('(') {
+            return Some(Cfg::Other(name.into()));
+        }
+        let mut list = Vec::new();
+        while !self.eat(')') {
+            list.push(self.cfg()?);
+            if !self.eat(',') && !self.peek(')') {
+                return None;
+            }
+        }
+        match name {
+            "all" => Some(Cfg::All(list)),
+            "any" => Some(Cfg::Any(list)),
+            "not" if list.len() == 1 => Some(Cfg::Not(Box::new(list.pop()?))),
+            _ => None,
+        }
+    }
+
+    fn skip_ws(&mut self) {
+        self.s = self.s.trim_start();
+    }
+
+    fn peek(&mut self, c: char) -> bool {
+        self.skip_ws();
+        self.s.starts_with(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek(c) {
+            self.s = &self.s[c.len_utf8()..];
+            true
+        } else {
+            false
+        }
+    }
+
+    fn ident(&mut self) -> Option<&'a str> {
+        self.skip_ws();
+        let end = self
+            .s
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(self.s.len());
+        if end == 0 {
+            return None;
+        }
+        let (ident, rest) = self.s.split_at(end);
+        self.s = rest;
+        Some(ident)
+    }
+
+    fn string(&mut self) -> Option<&'a str> {
+        if !self.eat('"') {
+            return None;
+        }
+        let end = self.s.find('"')?;
+        let (value, rest) = self.s.split_at(end);
+        self.s = &rest[1..];
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(cfg: &str) -> Option<Cfg> {
+        Cfg::parse_attr(&format!("#[doc(cfg({}))]", cfg))
+    }
+
+    fn feature(name: &str) -> Cfg {
+        Cfg::Feature(name.into())
+    }
+
+    fn features(names: &[&str]) -> HashSet<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_predicates() {
+        assert_eq!(parse("feature = \"std\""), Some(feature("std")));
+        assert_eq!(parse("unix"), Some(Cfg::Other("unix".into())));
+        assert_eq!(
+            parse("target_os = \"linux\""),
+            Some(Cfg::Other("target_os = \"linux\"".into()))
+        );
+        assert_eq!(
+            parse(" all( feature = \"a\" , not(feature = \"b\"), ) "),
+            Some(Cfg::All(vec![
+                feature("a"),
+                Cfg::Not(Box::new(feature("b")))
+            ]))
+        );
+        assert_eq!(
+            parse("any(unix, windows)"),
+            Some(Cfg::Any(vec![
+                Cfg::Other("unix".into()),
+                Cfg::Other("windows".into())
+            ]))
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_predicates() {
+        assert_eq!(parse("feature = std"), None);
+        assert_eq!(parse("not(unix, windows)"), None);
+        assert_eq!(parse("some(unix)"), None);
+        assert_eq!(parse("all(unix windows)"), None);
+        assert_eq!(parse("unix) extra"), None);
+        assert_eq!(Cfg::parse_attr("#[doc(hidden)]"), None);
+    }
+
+    #[test]
+    fn combines_attributes() {
+        let attrs = vec![
+            "#[must_use]".to_string(),
+            "#[doc(cfg(feature = \"a\"))]".to_string(),
+            "#[doc(cfg(unix))]".to_string(),
+        ];
+        assert_eq!(
+            Cfg::from_attrs(&attrs),
+            Some(Cfg::All(vec![feature("a"), Cfg::Other("unix".into())]))
+        );
+        assert_eq!(Cfg::from_attrs(&attrs[..2]), Some(feature("a")));
+        assert_eq!(Cfg::from_attrs(&attrs[..1]), None);
+
+        let mut names = BTreeSet::new();
+        parse("any(feature = \"b\", not(feature = \"a\"))")
+            .unwrap()
+            .features(&mut names);
+        assert_eq!(names.into_iter().collect::<Vec<_>>(), ["a", "b"]);
+    }
+
+    #[test]
+    fn evaluates_with_features() {
+        let a = features(&["a"]);
+        assert_eq!(feature("a").eval(&a), Some(true));
+        assert_eq!(feature("b").eval(&a), Some(false));
+        assert_eq!(Cfg::Other("unix".into()).eval(&a), None);
+
+        let all = parse("all(feature = \"a\", unix)").unwrap();
+        assert_eq!(all.eval(&a), None);
+        assert_eq!(all.eval(&features(&[])), Some(false));
+        let any = parse("any(feature = \"a\", unix)").unwrap();
+        assert_eq!(any.eval(&a), Some(true));
+        assert_eq!(any.eval(&features(&[])), None);
+        assert_eq!(parse("not(feature = \"a\")").unwrap().eval(&a), Some(false));
+
+        // only what is known to be false hides an item
+        assert!(all.enabled(&a));
+        assert!(!all.enabled(&features(&[])));
+        assert!(any.enabled(&features(&[])));
+    }
+
+    #[test]
+    fn describes_like_rustdoc() {
+        let cfg =
+            parse("all(feature = \"std\", not(feature = \"alloc\"), any(unix, windows))").unwrap();
+        assert_eq!(
+            cfg.describe(),
+            "crate feature `std` and non-crate feature `alloc` and (`unix` or `windows`)"
+        );
+        assert_eq!(
+            cfg.note(),
+            format!("Available on **{}** only.", cfg.describe())
+        );
+        assert_eq!(cfg.short(), "std & !alloc & (unix | windows)");
+
+        let cfg = parse("any(unix, all(windows, feature = \"std\"))").unwrap();
+        assert_eq!(
+            cfg.describe(),
+            "`unix` or (`windows` and crate feature `std`)"
+        );
+        assert_eq!(cfg.short(), "unix | windows & std");
+
+        let cfg = parse("not(all(feature = \"std\", any(unix, windows)))").unwrap();
+        assert_eq!(
+            cfg.describe(),
+            "not (crate feature `std` and (`unix` or `windows`))"
+        );
+        assert_eq!(cfg.short(), "!(std & (unix | windows))");
+    }
+}
@@ -0,0 +1,238 @@
+use std::env;
+use std::path::PathBuf;
+use std::process;
+
+use crate::cargo;
+
+const USAGE: &str = "\
+usage: druid-rustdoc [OPTIONS] [PATH]
+       druid-rustdoc markdown [OPTIONS] PATH [ITEM]
+       druid-rustdoc export-html [OPTIONS] PATH --out <DIR>
+       druid-rustdoc show [OPTIONS] PATH ITEM
+       druid-rustdoc tui [OPTIONS] PATH
+
+PATH is a rustdoc JSON file, or a Cargo.toml / crate directory to document
+with the nightly toolchain. Without it a start screen to pick a crate is shown.
+
+commands:
+  markdown      print the page of ITEM (a path like `krate::module::Struct`,
+                the crate root by default) as Markdown, or with `--out` write
+                it and all pages below it to a directory
+  export-html   write the whole crate to `--out` as a static HTML site
+  show          print the signature, docs and impls of ITEM to the terminal
+  tui           browse the docs in the terminal instead of a window
+
+options:
+  -p, --package <NAME>        package to document in a workspace
+  -F, --features <FEATURES>   comma separated features to enable
+      --all-features          enable all features
+      --no-default-features   disable the default features
+      --document-private-items
+                              include private items
+      --target-dir <DIR>      directory for the build artifacts
+      --no-cache              always parse the JSON instead of using the index cache
+  -o, --out <DIR>             directory to export to
+  -h, --help                  print this message";
+
+const COMMANDS: &[&str] = &["markdown", "export-html", "show", "tui"];
+
+/// A command that runs without opening a window.
+pub enum Command {
+    Markdown {
+        item: Option<String>,
+        out: Option<PathBuf>,
+    },
+    Html {
+        out: PathBuf,
+    },
+    Show {
+        item: String,
+    },
+    Tui,
+}
+
+pub struct Args {
+    /// Opens the window when `None`.
+    pub command: Option<Command>,
+    pub path: Option<PathBuf>,
+    pub build: cargo::Options,
+    pub no_cache: bool,
+}
+
+/// Parses the command line, exiting with the usage on errors.
+pub fn parse() -> Args {
+    parse_from(env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("error: {}\n\n{}", e, USAGE);
+        process::exit(2);
+    })
+}
+
+fn parse_from(args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut args = args.peekable();
+    let command = args.next_if(|arg| COMMANDS.contains(&arg.as_str()));
+    let mut path = None;
+    let mut item = None;
+    let mut out = None;
+    let mut build = cargo::Options::default();
+    let mut no_cache = false;
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("`{}` expects a value", arg))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            "-p" | "--package" => build.package = Some(value()?),
+            "-F" | "--features" => build.features.extend(
+                value()?
+                    .split([',', ' '])
+                    .filter(|f| !f.is_empty())
+                    .map(String::from),
+            ),
+            "--all-features" => build.all_features = true,
+            "--no-default-features" => build.no_default_features = true,
+            "--document-private-items" => build.document_private_items = true,
+            "--target-dir" => build.target_dir = Some(value()?.into()),
+            "--no-cache" => no_cache = true,
+            "-o" | "--out" if command.is_some() => out = Some(value()?.into()),
+            _ if arg.starts_with('-') => return Err(format!("unknown option `{}`", arg)),
+            _ if path.is_none() => path = Some(arg.into()),
+            _ if matches!(command.as_deref(), Some("markdown" | "show")) && item.is_none() => {
+                item = Some(arg)
+            }
+            _ => return Err(format!("unexpected argument `{}`", arg)),
+        }
+    }
+    let command = match command.as_deref() {
+        Some(name) if path.is_none() => return Err(format!("`{}` expects a PATH", name)),
+        Some("markdown") => Some(Command::Markdown { item, out }),
+        Some(name @ ("show" | "tui")) if out.is_some() => {
+            return Err(format!("`{}` takes no `--out`", name))
+        }
+        Some("show") => Some(Command::Show {
+            item: item.ok_or("`show` expects an ITEM")?,
+        }),
+        Some("tui") => Some(Command::Tui),
+        Some(name) => Some(Command::Html {
+            out: out.ok_or_else(|| format!("`{}` expects `--out`", name))?,
+        }),
+        None => None,
+    };
+    Ok(Args {
+        command,
+        path,
+        build,
+        no_cache,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        parse_from(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn error(args: &[&str]) -> String {
+        match parse(args) {
+            Ok(_) => panic!("parsed {:?}", args),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn window() {
+        let args = parse(&[]).unwrap();
+        assert!(args.command.is_none());
+        assert!(args.path.is_none());
+
+        let args = parse(&[
+            "Cargo.toml",
+            "-p",
+            "core",
+            "-F",
+            "serde,std",
+            "--features",
+            "alloc rc",
+            "--no-default-features",
+            "--target-dir",
+            "target",
+            "--no-cache",
+        ])
+        .unwrap();
+        assert!(args.command.is_none());
+        assert_eq!(args.path, Some("Cargo.toml".into()));
+        assert_eq!(args.build.package.as_deref(), Some("core"));
+        assert_eq!(args.build.features, ["serde", "std", "alloc", "rc"]);
+        assert!(args.build.no_default_features);
+        assert!(!args.build.all_features);
+        assert_eq!(args.build.target_dir, Some("target".into()));
+        assert!(args.no_cache);
+    }
+
+    #[test]
+    fn commands() {
+        let args = parse(&["markdown", "doc.json", "krate::Struct", "-o", "out"]).unwrap();
+        assert_eq!(args.path, Some("doc.json".into()));
+        match args.command {
+            Some(Command::Markdown { item, out }) => {
+                assert_eq!(item.as_deref(), Some("krate::Struct"));
+                assert_eq!(out, Some("out".into()));
+            }
+            _ => panic!("not markdown"),
+        }
+        assert!(matches!(
+            parse(&["markdown", "doc.json"]).unwrap().command,
+            Some(Command::Markdown {
+                item: None,
+                out: None
+            })
+        ));
+        match parse(&["show", "doc.json", "krate::f"]).unwrap().command {
+            Some(Command::Show { item }) => assert_eq!(item, "krate::f"),
+            _ => panic!("not show"),
+        }
+        match parse(&["export-html", "--out", "site", "doc.json"])
+            .unwrap()
+            .command
+        {
+            Some(Command::Html { out }) => assert_eq!(out, PathBuf::from("site")),
+            _ => panic!("not export-html"),
+        }
+        assert!(matches!(
+            parse(&["tui", "doc.json"]).unwrap().command,
+            Some(Command::Tui)
+        ));
+        // a command name after the path is the item, not a command
+        match parse(&["show", "doc.json", "tui"]).unwrap().command {
+            Some(Command::Show { item }) => assert_eq!(item, "tui"),
+            _ => panic!("not show"),
+        }
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(error(&["--frobnicate"]), "unknown option `--frobnicate`");
+        assert_eq!(error(&["a.json", "b.json"]), "unexpected argument `b.json`");
+        assert_eq!(
+            error(&["tui", "doc.json", "item"]),
+            "unexpected argument `item`"
+        );
+        assert_eq!(error(&["a.json", "--out", "dir"]), "unknown option `--out`");
+        assert_eq!(error(&["a.json", "-p"]), "`-p` expects a value");
+        assert_eq!(error(&["markdown"]), "`markdown` expects a PATH");
+        assert_eq!(error(&["show", "doc.json"]), "`show` expects an ITEM");
+        assert_eq!(
+            error(&["tui", "doc.json", "-o", "dir"]),
+            "`tui` takes no `--out`"
+        );
+        assert_eq!(
+            error(&["export-html", "doc.json"]),
+            "`export-html` expects `--out`"
+        );
+    }
+}
@@ -1,8 +1,81 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
 use druid::im::Vector;
-use druid::text::RichText;
 use druid::Data;
 use druid_enums::Matcher;
-use rustdoc_types::{FnDecl, Generics, Qualifiers, Type};
+use rustdoc_types::{Deprecation, FnDecl, Generics, Qualifiers, StructType, Type, Visibility};
+use serde::{Deserialize, Serialize};
+
+use crate::cfg::Cfg;
+use crate::format::PagePaths;
+
+#[derive(Data, Clone, Debug)]
+pub struct App {
+    pub page: Page,
+    /// Short message shown at the bottom of the window, e.g. after a reload.
+    pub notification: Option<String>,
+    /// Whether the loaded crate was documented with private items.
+    pub includes_private: bool,
+    pub show_private: bool,
+    /// Show full paths instead of the last segment in signatures.
+    pub full_paths: bool,
+    /// Only list items with this attribute.
+    pub attr_filter: Option<String>,
+    pub hide_deprecated: bool,
+    /// The crate features seen in `#[doc(cfg(..))]`, toggled in the feature bar.
+    pub features: Vector<Feature>,
+    /// Only list items available with the enabled features.
+    pub feature_filter: bool,
+}
+
+#[derive(Data, Clone, Debug)]
+pub struct Feature {
+    pub name: String,
+    pub enabled: bool,
+}
+
+#[derive(Data, Clone, Debug, Matcher)]
+pub enum Page {
+    #[matcher(builder_name = welcome)]
+    Welcome(Welcome),
+    #[matcher(builder_name = loading)]
+    Loading(Loading),
+    #[matcher(builder_name = docs)]
+    Docs(Screen),
+}
+
+#[derive(Data, Clone, Debug)]
+pub struct Welcome {
+    pub recent: Vector<Recent>,
+}
+
+/// A previously opened crate, persisted between runs.
+#[derive(Data, Clone, Debug, Serialize, Deserialize)]
+pub struct Recent {
+    pub name: String,
+    pub version: Option<String>,
+    /// The JSON file or cargo project that was opened.
+    pub path: Arc<PathBuf>,
+}
+
+#[derive(Data, Clone, Debug)]
+pub struct Loading {
+    pub title: String,
+    pub progress: Option<Progress>,
+    /// Output of the build so far, one entry per line.
+    pub log: Vector<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Data, Clone, Debug, PartialEq)]
+pub enum Progress {
+    ReadingCache,
+    Reading { read: u64, total: u64 },
+    Parsing,
+    /// Collecting the features and building the page of the crate root.
+    BuildingPage,
+}
 
 #[derive(Data, Clone, Debug, Matcher)]
 pub enum Screen {
@@ -14,6 +87,8 @@ pub enum Screen {
     Enum(Enum),
     #[matcher(builder_name = fn_)]
     Fn(Fn),
+    #[matcher(builder_name = index)]
+    Index(Index),
 }
 
 #[derive(Data, Clone, Debug)]
@@ -21,8 +96,33 @@ pub struct Item {
     pub name: String,
     pub parents: Vector<String>,
     pub id: rustdoc_types::Id,
+    pub visibility: Visibility,
+    /// Not part of the public API, shown dimmed.
+    pub private: bool,
+    /// The attributes worth showing, in source form.
+    pub attrs: Vector<String>,
+    pub deprecation: Option<Deprecation>,
+    /// Parsed from `#[doc(cfg(..))]`.
+    pub cfg: Option<Arc<Cfg>>,
     pub short_doc: Option<String>,
-    pub doc: Option<RichText>,
+    pub doc: Option<Doc>,
+    /// How types are named on the page the item is shown on.
+    pub paths: Arc<PagePaths>,
+}
+
+/// Markdown docs of an item, rendered lazily by the view.
+#[derive(Data, Clone, Debug)]
+pub struct Doc {
+    pub id: rustdoc_types::Id,
+    pub markdown: Arc<str>,
+}
+
+/// A page listing items from all over the crate, like the deprecated ones.
+#[derive(Data, Clone, Debug)]
+pub struct Index {
+    pub title: String,
+    /// The items, `id` is the page they are shown on.
+    pub items: Vector<Item>,
 }
 
 #[derive(Data, Clone, Debug)]
@@ -38,8 +138,11 @@ pub struct Mod {
 #[derive(Data, Clone, Debug)]
 pub struct Struct {
     pub item: Item,
+    pub struct_type: StructType,
     pub generics: Generics,
     pub fields: Vector<Field>,
+    /// Some fields are private and not shown.
+    pub fields_stripped: bool,
     pub impls: Vector<Impl>,
     pub trait_impls: Vector<Impl>,
     pub auto_impls: Vector<Impl>,
@@ -50,6 +153,7 @@ pub struct Enum {
     pub item: Item,
     pub generics: Generics,
     pub variants: Vector<Variant>,
+    pub variants_stripped: bool,
     pub impls: Vector<Impl>,
     pub trait_impls: Vector<Impl>,
     pub auto_impls: Vector<Impl>,
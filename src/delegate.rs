@@ -1,52 +1,515 @@
-use std::fs;
-use std::path::Path;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use druid::im::Vector;
-use druid::{AppDelegate, Selector};
+use druid::{
+    commands, AppDelegate, Application, Clipboard, Code, Event, ExtEventSink, FileDialogOptions,
+    FileSpec, HotKey, MouseButton, Selector, SysMods,
+};
 use rdoc::{ItemEnum, ItemKind};
 use rustdoc_types as rdoc;
 pub const OPEN_LINK: Selector<String> = Selector::new("druid-rustdoc.open-link");
 
-use crate::md::markdown_to_text;
-use crate::{data, GOTO_ITEM};
+use crate::browse::{Action, Settings};
+use crate::cargo::{self, BUILD_LOG};
+use crate::cfg::Cfg;
+use crate::load::{self, LOADED, PROGRESS};
+use crate::watch::{self, RELOAD};
+use crate::{
+    attrs, data, export, format, md, recent, CYCLE_ATTR_FILTER, EXPORT_MARKDOWN, GOTO_ITEM,
+    GO_BACK, OPEN_PATH, SHOW_DEPRECATED, TOGGLE_DEPRECATED, TOGGLE_FEATURE, TOGGLE_FEATURE_FILTER,
+    TOGGLE_FULL_PATHS, TOGGLE_PRIVATE,
+};
 
 pub struct Delegate {
+    sink: ExtEventSink,
+    /// Whether loading may use the index cache.
+    cache: bool,
+    build: cargo::Options,
+    /// The JSON file or cargo project that was opened last.
+    path: Option<PathBuf>,
+    /// Counts the crates opened, what loads and watchers submit is ignored unless it is for the
+    /// last one.
+    generation: usize,
+    /// `None` while the crate is still loading.
+    docs: Option<Docs>,
+}
+
+impl Delegate {
+    pub fn new(sink: ExtEventSink, cache: bool, build: cargo::Options) -> Self {
+        Self {
+            sink,
+            cache,
+            build,
+            path: None,
+            generation: 0,
+            docs: None,
+        }
+    }
+
+    /// Starts loading the JSON file or cargo project at `path`, returning the loading page. Its
+    /// first page is built with `settings`.
+    pub fn open(&mut self, path: PathBuf, settings: Settings) -> data::Page {
+        self.generation += 1;
+        watch::stop_others(self.generation);
+        self.docs = None;
+        let title = match cargo::manifest_path(&path) {
+            Some(manifest) => {
+                let name = manifest
+                    .canonicalize()
+                    .ok()
+                    .and_then(|p| Some(p.parent()?.file_name()?.to_string_lossy().into_owned()))
+                    .unwrap_or_default();
+                let sink = self.sink.clone();
+                let build = self.build.clone();
+                cargo::build(manifest, build, self.cache, settings, self.generation, sink);
+                format!("Documenting {}", name)
+            }
+            None => {
+                let sink = self.sink.clone();
+                load::load(path.clone(), self.cache, settings, self.generation, sink);
+                format!("Loading {}", path.display())
+            }
+        };
+        self.path = Some(path);
+        data::Page::Loading(data::Loading {
+            title,
+            progress: None,
+            log: Vector::new(),
+            error: None,
+        })
+    }
+
+    /// Remembers the crate that was just loaded in the recent crates.
+    fn add_recent(&self, krate: &rdoc::Crate) {
+        if let Some(path) = &self.path {
+            let name = krate.index.get(&krate.root).and_then(|i| i.name.clone());
+            recent::add(data::Recent {
+                name: name.unwrap_or_default(),
+                version: krate.crate_version.clone(),
+                path: Arc::new(path.clone()),
+            });
+        }
+    }
+}
+
+/// The extensions of rustdoc JSON, plain or compressed.
+const JSON_EXTENSIONS: &[&str] = &["json", "gz", "zst"];
+
+/// The clipboard formats file managers put copied files in, a `file://` URI per line.
+const FILE_LIST_FORMATS: &[&str] = &["text/uri-list", "public.file-url"];
+
+/// Options for the file dialog to open a crate.
+pub fn open_dialog() -> FileDialogOptions {
+    FileDialogOptions::new().allowed_types(vec![
+        FileSpec::new("rustdoc JSON", JSON_EXTENSIONS),
+        FileSpec::new("Cargo manifest", &["toml"]),
+    ])
+}
+
+/// The first crate among the files copied in a file manager, or absolute paths copied as text.
+/// Pasting stands in for dropping files onto the window, druid has no events for that.
+fn pasted_crate(clipboard: &Clipboard) -> Option<PathBuf> {
+    let list = match clipboard.preferred_format(FILE_LIST_FORMATS) {
+        Some(format) => String::from_utf8(clipboard.get_format(format)?).ok()?,
+        None => clipboard.get_string()?,
+    };
+    pasted_paths(&list).into_iter().find(|p| can_open(p))
+}
+
+/// The paths in a list of files, one `file://` URI or absolute path per line.
+fn pasted_paths(list: &str) -> Vec<PathBuf> {
+    list.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| match line.strip_prefix("file://") {
+            // skip the host, empty for local files
+            Some(uri) => {
+                let path = percent_decode(&uri[uri.find('/')?..]);
+                // `/C:/dir` on windows
+                let path = match path.get(2..3) {
+                    Some(":") if cfg!(windows) => path[1..].to_string(),
+                    _ => path,
+                };
+                Some(PathBuf::from(path))
+            }
+            None => Some(PathBuf::from(line)).filter(|p| p.is_absolute()),
+        })
+        .collect()
+}
+
+/// Replaces the `%XX` escapes of a URI with the bytes they stand for.
+fn percent_decode(s: &str) -> String {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut rest = s.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        let escaped = tail
+            .get(..2)
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) if b == b'%' => {
+                bytes.push(byte);
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(b);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Whether `path` is rustdoc JSON or a cargo project.
+fn can_open(path: &Path) -> bool {
+    let is_json = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map_or(false, |e| JSON_EXTENSIONS.contains(&e));
+    (is_json && path.is_file()) || cargo::manifest_path(path).is_some()
+}
+
+/// Picks the directory to export Markdown to.
+pub fn export_dialog() -> FileDialogOptions {
+    FileDialogOptions::new()
+        .select_directories()
+        .title("Export as Markdown")
+        .button_text("Export")
+        .accept_command(EXPORT_MARKDOWN)
+}
+
+/// A crate ready to be shown, prepared on the thread that loaded it.
+pub struct Loaded {
+    pub docs: Docs,
+    /// The features of the crate, for the feature bar.
+    pub features: Vector<data::Feature>,
+    /// The page of the crate root.
+    pub screen: data::Screen,
+}
+
+impl Loaded {
+    pub fn new(krate: rdoc::Crate, settings: &Settings) -> Self {
+        let mut docs = Docs::new(krate);
+        let mut settings = settings.clone();
+        settings.load_features(&docs);
+        docs.set_settings(&settings);
+        let screen = docs.data();
+        Self {
+            docs,
+            features: settings.features,
+            screen,
+        }
+    }
+}
+
+/// A loaded crate and the navigation state in it.
+pub struct Docs {
     krate: rdoc::Crate,
     current: rdoc::Id,
+    history: Vec<rdoc::Id>,
+    /// Screens built so far, cleared on reload.
+    screens: HashMap<rdoc::Id, data::Screen>,
+    /// Whether items that aren't public are listed.
+    show_private: bool,
+    /// Only list items with this attribute, one of [`attrs::SHOWN`].
+    attr_filter: Option<String>,
+    hide_deprecated: bool,
+    /// Only list items available with these features, all items are listed when `None`.
+    features: Option<HashSet<String>>,
+    /// Whether types are named by their full paths.
+    full_paths: bool,
 }
 
-impl Delegate {
-    pub fn new(path: &Path) -> Self {
-        let data = fs::read_to_string(path).unwrap();
-        let krate = rustdoc_types::parse(&data);
+impl Docs {
+    pub fn new(krate: rdoc::Crate) -> Self {
         Self {
             current: krate.root.clone(),
+            history: Vec::new(),
+            screens: HashMap::new(),
+            show_private: true,
+            attr_filter: None,
+            hide_deprecated: false,
+            features: None,
+            full_paths: false,
             krate,
         }
     }
 
-    pub fn data(&self) -> data::Screen {
-        let item = &self.krate.index[&self.current];
-        let s = self.krate.paths.get(&self.current);
+    pub fn set_show_private(&mut self, show: bool) {
+        if show != self.show_private {
+            self.show_private = show;
+            self.screens.clear();
+        }
+    }
+
+    pub fn set_attr_filter(&mut self, filter: Option<String>) {
+        if filter != self.attr_filter {
+            self.attr_filter = filter;
+            self.screens.clear();
+        }
+    }
+
+    pub fn set_hide_deprecated(&mut self, hide: bool) {
+        if hide != self.hide_deprecated {
+            self.hide_deprecated = hide;
+            self.screens.clear();
+        }
+    }
+
+    pub fn set_features(&mut self, features: Option<HashSet<String>>) {
+        if features != self.features {
+            self.features = features;
+            self.screens.clear();
+        }
+    }
+
+    pub fn set_full_paths(&mut self, full: bool) {
+        if full != self.full_paths {
+            self.full_paths = full;
+            self.screens.clear();
+        }
+    }
+
+    /// The features mentioned in `#[doc(cfg(..))]`, features disabled in `previous` stay disabled.
+    pub fn features(&self, previous: &Vector<data::Feature>) -> Vector<data::Feature> {
+        let mut names = BTreeSet::new();
+        for item in self.krate.index.values() {
+            if let Some(cfg) = Cfg::from_attrs(&item.attrs) {
+                cfg.features(&mut names);
+            }
+        }
+        names
+            .into_iter()
+            .map(|name| data::Feature {
+                enabled: !previous.iter().any(|f| f.name == name && !f.enabled),
+                name,
+            })
+            .collect()
+    }
+
+    /// Whether `item` is shown, callers skip this for items that inherit their visibility.
+    fn shown(&self, item: &rdoc::Item) -> bool {
+        self.show_private || item.visibility == rdoc::Visibility::Public
+    }
+
+    /// Whether `item` is shown in module listings and impls, where the attribute and feature
+    /// filters apply.
+    fn listed(&self, item: &rdoc::Item) -> bool {
+        let has_attr = |filter: &String| {
+            item.attrs
+                .iter()
+                .filter_map(|a| attrs::relevant(a))
+                .any(|a| attrs::name(&a) == Some(filter.as_str()))
+        };
+        self.shown(item)
+            && !(self.hide_deprecated && item.deprecation.is_some())
+            && self.attr_filter.as_ref().map_or(true, has_attr)
+            && self.features.as_ref().map_or(true, |features| {
+                Cfg::from_attrs(&item.attrs).map_or(true, |cfg| cfg.enabled(features))
+            })
+    }
+
+    pub fn goto(&mut self, id: &rdoc::Id) {
+        if *id != self.current {
+            self.history.push(self.current.clone());
+        }
+        self.current = id.clone();
+    }
+
+    /// Returns to the previous item, if there is one.
+    pub fn back(&mut self) -> bool {
+        match self.history.pop() {
+            Some(id) => {
+                self.current = id;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn krate(&self) -> &rdoc::Crate {
+        &self.krate
+    }
+
+    /// The item currently shown.
+    pub fn current(&self) -> &rdoc::Id {
+        &self.current
+    }
+
+    /// The screen of the current item.
+    pub fn data(&mut self) -> data::Screen {
+        let current = self.current.clone();
+        self.page(&current)
+    }
+
+    /// The screen of `id`, which must have a page.
+    pub fn page(&mut self, id: &rdoc::Id) -> data::Screen {
+        if let Some(screen) = self.screens.get(id) {
+            return screen.clone();
+        }
+        let mut screen = self.screen(id);
+        let paths = Arc::new(self.page_paths(&screen));
+        set_paths(&mut screen, &paths);
+        self.screens.insert(id.clone(), screen.clone());
+        screen
+    }
+
+    /// Finds the crate-local item with a page at `path`, like `krate::module::Struct`. The crate
+    /// name can be left out or written as `crate`. When items of different kinds share the path,
+    /// like a module and a function, the first kind of [`PAGE_KINDS`] wins.
+    pub fn find(&self, path: &str) -> Option<rdoc::Id> {
+        let segments: Vec<_> = path.split("::").collect();
+        let relative = match segments.split_first() {
+            Some((&"crate", rest)) => rest,
+            _ => &segments[..],
+        };
+        let matches = |s: &rdoc::ItemSummary| {
+            let path = s.path.iter().map(String::as_str);
+            path.clone().eq(segments.iter().copied()) || path.skip(1).eq(relative.iter().copied())
+        };
+        self.krate
+            .paths
+            .iter()
+            .filter(|(id, s)| {
+                s.crate_id == 0
+                    && has_page(&s.kind)
+                    && self.krate.index.contains_key(id)
+                    && matches(s)
+            })
+            // the paths are a hash map, order the matches so the same one is always found
+            .min_by_key(|(id, s)| {
+                let kind = PAGE_KINDS.iter().position(|k| *k == s.kind);
+                (kind, id.0.clone())
+            })
+            .map(|(id, _)| id.clone())
+    }
+
+    /// The shown crate-local items with a page whose name contains `query`, ignoring case.
+    /// Exact matches come first, then shorter paths. An empty query finds nothing.
+    pub fn search(&self, query: &str) -> Vec<rdoc::Id> {
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let query = query.to_lowercase();
+        let mut found: Vec<_> = self
+            .krate
+            .paths
+            .iter()
+            .filter(|(id, s)| {
+                s.crate_id == 0
+                    && has_page(&s.kind)
+                    && self.krate.index.get(id).map_or(false, |i| self.shown(i))
+                    && s.path
+                        .last()
+                        .map_or(false, |name| name.to_lowercase().contains(&query))
+            })
+            .collect();
+        found.sort_by_key(|(_, s)| {
+            let exact = s.path.last().map(|name| name.to_lowercase()) == Some(query.clone());
+            (!exact, s.path.len(), s.path.clone())
+        });
+        found.into_iter().map(|(id, _)| id.clone()).collect()
+    }
+
+    /// Collects the full paths of the types on `screen`, marking those whose short names clash.
+    fn page_paths(&self, screen: &data::Screen) -> format::PagePaths {
+        let mut paths = format::PagePaths {
+            show_full: self.full_paths,
+            ..format::PagePaths::default()
+        };
+        let mut by_name: HashMap<String, rdoc::Id> = HashMap::new();
+        let mut add = |id: &rdoc::Id, name: &str| {
+            let short = name.rsplit("::").next().unwrap().to_string();
+            match by_name.get(&short) {
+                Some(other) if other != id => {
+                    paths.ambiguous.insert(other.clone());
+                    paths.ambiguous.insert(id.clone());
+                }
+                Some(_) => {}
+                None => {
+                    by_name.insert(short, id.clone());
+                }
+            }
+            let full = match self.krate.paths.get(id) {
+                Some(s) => s.path.iter().cloned().collect::<Vec<_>>().join("::"),
+                None => name.to_string(),
+            };
+            paths.full.insert(id.clone(), full);
+            if opens(&self.krate, id) {
+                paths.pages.insert(id.clone());
+            }
+        };
+        match screen {
+            data::Screen::Mod(_) | data::Screen::Index(_) => {}
+            data::Screen::Struct(s) => {
+                format::visit_generics_paths(&s.generics, &mut add);
+                for f in &s.fields {
+                    format::visit_ty_paths(&f.ty, &mut add);
+                }
+                for impls in &[&s.impls, &s.trait_impls, &s.auto_impls] {
+                    visit_impl_paths(impls, &mut add);
+                }
+            }
+            data::Screen::Enum(e) => {
+                format::visit_generics_paths(&e.generics, &mut add);
+                for v in &e.variants {
+                    match &v.inner {
+                        data::VariantInner::Plain => {}
+                        data::VariantInner::Tuple(ts) => ts
+                            .iter()
+                            .for_each(|ty| format::visit_ty_paths(ty, &mut add)),
+                        data::VariantInner::Struct(fs) => fs
+                            .iter()
+                            .for_each(|f| format::visit_ty_paths(&f.ty, &mut add)),
+                    }
+                }
+                for impls in &[&e.impls, &e.trait_impls, &e.auto_impls] {
+                    visit_impl_paths(impls, &mut add);
+                }
+            }
+            data::Screen::Fn(f) => {
+                format::visit_generics_paths(&f.generics, &mut add);
+                format::visit_decl_paths(&f.decl, &mut add);
+            }
+        }
+        paths
+    }
+
+    fn screen(&self, id: &rdoc::Id) -> data::Screen {
+        if *id == deprecated_id() {
+            return data::Screen::Index(self.deprecated_index());
+        }
+        let item = match self.krate.index.get(id) {
+            Some(item) => item,
+            None => return data::Screen::Index(self.no_page(id)),
+        };
+        let s = self.krate.paths.get(id);
         match &item.inner {
             ItemEnum::ModuleItem(m) => {
-                let item = item_to_data(&item, s);
-                let item_of_kind = |k| {
-                    m.items
-                        .iter()
-                        .map(|id| (&self.krate.index[id], self.krate.paths.get(id)))
-                        .filter(|(i, _)| i.kind == k)
-                        .map(|(i, sum)| item_to_data(i, sum))
-                        .collect()
-                };
-                let mod_ = data::Mod {
-                    item,
-                    structs: item_of_kind(ItemKind::Struct),
-                    enums: item_of_kind(ItemKind::Enum),
-                    traits: item_of_kind(ItemKind::Trait),
-                    mods: item_of_kind(ItemKind::Module),
-                    fns: item_of_kind(ItemKind::Function),
+                let mut mod_ = data::Mod {
+                    item: item_to_data(&item, s),
+                    structs: Vector::new(),
+                    enums: Vector::new(),
+                    traits: Vector::new(),
+                    mods: Vector::new(),
+                    fns: Vector::new(),
                 };
+                for id in &m.items {
+                    let item = &self.krate.index[id];
+                    if !self.listed(item) {
+                        continue;
+                    }
+                    let list = match item.kind {
+                        ItemKind::Struct => &mut mod_.structs,
+                        ItemKind::Enum => &mut mod_.enums,
+                        ItemKind::Trait => &mut mod_.traits,
+                        ItemKind::Module => &mut mod_.mods,
+                        ItemKind::Function => &mut mod_.fns,
+                        _ => continue,
+                    };
+                    list.push_back(item_to_data(item, self.krate.paths.get(id)));
+                }
                 data::Screen::Mod(mod_)
             }
             ItemEnum::StructItem(_) => {
@@ -55,6 +518,7 @@ impl Delegate {
             }
             ItemEnum::EnumItem(e) => {
                 let item = item_to_data(&item, s);
+                let (impls, trait_impls, auto_impls) = self.impls(&e.impls);
                 let enum_ = data::Enum {
                     item,
                     generics: e.generics.clone(),
@@ -64,24 +528,10 @@ impl Delegate {
                         .map(|id| (&self.krate.index[id], self.krate.paths.get(id)))
                         .map(|(item, s)| self.item_to_variant(item, s))
                         .collect(),
-                    impls: e
-                        .impls
-                        .iter()
-                        .map(|id| self.item_to_impl(id))
-                        .filter(|i| i.trait_.is_none())
-                        .collect(),
-                    trait_impls: e
-                        .impls
-                        .iter()
-                        .map(|id| self.item_to_impl(id))
-                        .filter(|i| i.trait_.is_some() && !i.synthetic)
-                        .collect(),
-                    auto_impls: e
-                        .impls
-                        .iter()
-                        .map(|id| self.item_to_impl(id))
-                        .filter(|i| i.synthetic)
-                        .collect(),
+                    variants_stripped: e.variants_stripped,
+                    impls,
+                    trait_impls,
+                    auto_impls,
                 };
                 data::Screen::Enum(enum_)
             }
@@ -89,14 +539,70 @@ impl Delegate {
                 let f = item_to_fn(item, s, f);
                 data::Screen::Fn(f)
             }
-            _ => todo!("handle other Items"),
+            _ => data::Screen::Index(self.no_page(id)),
         }
     }
 
+    /// An empty index saying that `id` has no page, for ids that can't be opened.
+    fn no_page(&self, id: &rdoc::Id) -> data::Index {
+        let name = match self.krate.paths.get(id) {
+            Some(s) => s.path.iter().cloned().collect::<Vec<_>>().join("::"),
+            None => id.0.clone(),
+        };
+        data::Index {
+            title: format!("No page for `{}`", name),
+            items: Vector::new(),
+        }
+    }
+
+    /// Replaces the crate, keeping the current item and history where they still exist.
+    fn reload(&mut self, krate: rdoc::Crate) {
+        let current = self
+            .find_in(&krate, &self.current)
+            .unwrap_or_else(|| krate.root.clone());
+        let history = self
+            .history
+            .iter()
+            .filter_map(|id| self.find_in(&krate, id))
+            .collect();
+        self.current = current;
+        self.history = history;
+        self.krate = krate;
+        self.screens.clear();
+    }
+
+    /// Finds the item `id` of the current crate in `krate`, by path if its id changed.
+    fn find_in(&self, krate: &rdoc::Crate, id: &rdoc::Id) -> Option<rdoc::Id> {
+        if *id == deprecated_id() {
+            return Some(id.clone());
+        }
+        if *id == self.krate.root {
+            return Some(krate.root.clone());
+        }
+        let old = self.krate.paths.get(id);
+        let same_id = krate.index.contains_key(id)
+            && match (old, krate.paths.get(id)) {
+                (Some(old), Some(new)) => old.path == new.path,
+                (None, None) => true,
+                _ => false,
+            };
+        if same_id {
+            return Some(id.clone());
+        }
+        let old = old?;
+        krate
+            .paths
+            .iter()
+            .find(|(id, s)| {
+                s.path == old.path && s.kind == old.kind && krate.index.contains_key(id)
+            })
+            .map(|(id, _)| id.clone())
+    }
+
     fn item_to_variant(&self, item: &rdoc::Item, s: Option<&rdoc::ItemSummary>) -> data::Variant {
         match &item.inner {
             ItemEnum::VariantItem(v) => data::Variant {
-                item: item_to_data(item, s),
+                item: inherited(item_to_data(item, s)),
                 inner: match v {
                     rdoc::Variant::Plain => data::VariantInner::Plain,
                     rdoc::Variant::Tuple(v) => data::VariantInner::Tuple(v.clone()),
@@ -104,12 +610,12 @@ impl Delegate {
                         i.iter()
                             .map(|id| {
                                 let item = &self.krate.index[id];
-                                let s = &self.krate.paths[id];
+                                let s = self.krate.paths.get(id);
                                 let ty = match &item.inner {
                                     ItemEnum::StructFieldItem(ty) => ty.clone(),
                                     _ => unreachable!(),
                                 };
-                                let item = item_to_data(item, Some(s));
+                                let item = inherited(item_to_data(item, s));
                                 data::Field { item, ty }
                             })
                             .collect(),
@@ -126,43 +632,96 @@ impl Delegate {
             _ => unreachable!(),
         };
         let item = item_to_data(&item, sum);
-        let struct_ = data::Struct {
+        let (impls, trait_impls, auto_impls) = self.impls(&s.impls);
+        let fields: Vector<_> = s
+            .fields
+            .iter()
+            .map(|id| &self.krate.index[id])
+            .filter(|item| self.shown(item))
+            .map(|item| {
+                let s = self.krate.paths.get(&item.id);
+                let ty = match &item.inner {
+                    ItemEnum::StructFieldItem(ty) => ty.clone(),
+                    _ => unreachable!(),
+                };
+                let item = item_to_data(item, s);
+                data::Field { item, ty }
+            })
+            .collect();
+        data::Struct {
             item,
+            struct_type: s.struct_type.clone(),
             generics: s.generics.clone(),
-            fields: s
-                .fields
-                .iter()
-                .map(|id| {
-                    let item = &self.krate.index[id];
-                    let s = self.krate.paths.get(id);
-                    let ty = match &item.inner {
-                        ItemEnum::StructFieldItem(ty) => ty.clone(),
+            // hidden by the private items toggle or left out by rustdoc
+            fields_stripped: s.fields_stripped || fields.len() < s.fields.len(),
+            fields,
+            impls,
+            trait_impls,
+            auto_impls,
+        }
+    }
+
+    /// Lists the deprecated items of the crate, members of types link to the page of the type.
+    fn deprecated_index(&self) -> data::Index {
+        let mut items = Vec::new();
+        for (id, item) in &self.krate.index {
+            let summary = match self.krate.paths.get(id) {
+                Some(s) if item.crate_id == 0 && self.shown(item) => s,
+                _ => continue,
+            };
+            let (members, impls) = match &item.inner {
+                ItemEnum::ModuleItem(_) | ItemEnum::FunctionItem(_) => (None, None),
+                ItemEnum::StructItem(s) => (Some(&s.fields), Some(&s.impls)),
+                ItemEnum::EnumItem(e) => (Some(&e.variants), Some(&e.impls)),
+                _ => continue,
+            };
+            if item.deprecation.is_some() {
+                items.push(item_to_data(item, Some(summary)));
+            }
+            let impl_items =
+                impls
+                    .into_iter()
+                    .flatten()
+                    .flat_map(|id| match &self.krate.index[id].inner {
+                        ItemEnum::ImplItem(i) => i.items.iter(),
                         _ => unreachable!(),
-                    };
-                    let item = item_to_data(item, s);
-                    data::Field { item, ty }
-                })
-                .collect(),
-            impls: s
-                .impls
-                .iter()
-                .map(|id| self.item_to_impl(id))
-                .filter(|i| i.trait_.is_none())
-                .collect(),
-            trait_impls: s
-                .impls
-                .iter()
-                .map(|id| self.item_to_impl(id))
-                .filter(|i| i.trait_.is_some() && !i.synthetic)
-                .collect(),
-            auto_impls: s
-                .impls
-                .iter()
-                .map(|id| self.item_to_impl(id))
-                .filter(|i| i.synthetic)
-                .collect(),
-        };
-        struct_
+                    });
+            for member in members.into_iter().flatten().chain(impl_items) {
+                let member = &self.krate.index[member];
+                if member.deprecation.is_some() {
+                    let mut member = item_to_data(member, None);
+                    member.parents = summary.path.clone();
+                    member.id = id.clone();
+                    items.push(member);
+                }
+            }
+        }
+        items.sort_by(|a, b| (&a.parents, &a.name).cmp(&(&b.parents, &b.name)));
+        data::Index {
+            title: "Deprecated items".into(),
+            items: items.into_iter().collect(),
+        }
+    }
+
+    /// Splits `ids` into inherent, trait and auto trait impls.
+    fn impls(
+        &self,
+        ids: &Vector<rdoc::Id>,
+    ) -> (Vector<data::Impl>, Vector<data::Impl>, Vector<data::Impl>) {
+        let mut impls = Vector::new();
+        let mut trait_impls = Vector::new();
+        let mut auto_impls = Vector::new();
+        for id in ids {
+            let impl_ = self.item_to_impl(id);
+            if impl_.synthetic {
+                auto_impls.push_back(impl_);
+            } else if impl_.trait_.is_some() {
+                trait_impls.push_back(impl_);
+            } else {
+                impls.push_back(impl_);
+            }
+        }
+        (impls, trait_impls, auto_impls)
     }
 
     fn item_to_impl(&self, id: &rdoc::Id) -> data::Impl {
@@ -170,21 +729,34 @@ impl Delegate {
         let s = self.krate.paths.get(id);
         match &item.inner {
             ItemEnum::ImplItem(i) => {
-                let item = item_to_data(item, s);
+                let item = inherited(item_to_data(item, s));
                 let mut fns = Vector::new();
                 let mut consts = Vector::new();
                 let mut tys = Vector::new();
 
+                // items of trait impls are as visible as the trait
+                let is_trait_impl = i.trait_.is_some();
                 for id in &i.items {
                     let item = &self.krate.index[id];
+                    if !is_trait_impl && !self.listed(item) {
+                        continue;
+                    }
                     let s = self.krate.paths.get(id);
                     match &item.inner {
-                        ItemEnum::FunctionItem(f) => fns.push_back(item_to_fn(item, s, f)),
+                        ItemEnum::FunctionItem(f) => {
+                            let mut f = item_to_fn(item, s, f);
+                            f.item.private &= !is_trait_impl;
+                            fns.push_back(f);
+                        }
                         ItemEnum::AssocConstItem { type_, default } => {
-                            consts.push_back(item_to_const(item, s, type_, default))
+                            let mut c = item_to_const(item, s, type_, default);
+                            c.item.private &= !is_trait_impl;
+                            consts.push_back(c);
                         }
                         ItemEnum::TypedefItem(t) => {
-                            tys.push_back(item_to_typedef(item, s, t));
+                            let mut t = item_to_typedef(item, s, t);
+                            t.item.private &= !is_trait_impl;
+                            tys.push_back(t);
                         }
                         _ => {}
                     }
@@ -246,10 +818,111 @@ fn item_to_fn(item: &rdoc::Item, s: Option<&rdoc::ItemSummary>, m: &rdoc::Functi
     }
 }
 
+fn visit_impl_paths(impls: &Vector<data::Impl>, add: &mut dyn FnMut(&rdoc::Id, &str)) {
+    for i in impls {
+        format::visit_generics_paths(&i.generics, add);
+        if let Some(tr) = &i.trait_ {
+            format::visit_ty_paths(tr, add);
+        }
+        format::visit_ty_paths(&i.for_, add);
+        for t in &i.tys {
+            format::visit_ty_paths(&t.ty, add);
+        }
+        for c in &i.consts {
+            format::visit_ty_paths(&c.ty, add);
+        }
+        for f in &i.fns {
+            format::visit_generics_paths(&f.generics, add);
+            format::visit_decl_paths(&f.decl, add);
+        }
+    }
+}
+
+/// Gives the items on `screen` the paths of its page.
+fn set_paths(screen: &mut data::Screen, paths: &Arc<format::PagePaths>) {
+    let mut set = |item: &mut data::Item| item.paths = paths.clone();
+    match screen {
+        data::Screen::Mod(_) | data::Screen::Index(_) => {}
+        data::Screen::Struct(s) => {
+            set(&mut s.item);
+            for f in s.fields.iter_mut() {
+                set(&mut f.item);
+            }
+            for impls in [&mut s.impls, &mut s.trait_impls, &mut s.auto_impls] {
+                set_impl_paths(impls, &mut set);
+            }
+        }
+        data::Screen::Enum(e) => {
+            set(&mut e.item);
+            for v in e.variants.iter_mut() {
+                set(&mut v.item);
+                if let data::VariantInner::Struct(fs) = &mut v.inner {
+                    fs.iter_mut().for_each(|f| set(&mut f.item));
+                }
+            }
+            for impls in [&mut e.impls, &mut e.trait_impls, &mut e.auto_impls] {
+                set_impl_paths(impls, &mut set);
+            }
+        }
+        data::Screen::Fn(f) => set(&mut f.item),
+    }
+}
+
+fn set_impl_paths(impls: &mut Vector<data::Impl>, set: &mut dyn FnMut(&mut data::Item)) {
+    for i in impls.iter_mut() {
+        set(&mut i.item);
+        i.tys.iter_mut().for_each(|t| set(&mut t.item));
+        i.consts.iter_mut().for_each(|c| set(&mut c.item));
+        i.fns.iter_mut().for_each(|f| set(&mut f.item));
+    }
+}
+
+/// The kinds of items shown on a page of their own.
+pub const PAGE_KINDS: [ItemKind; 4] = [
+    ItemKind::Module,
+    ItemKind::Struct,
+    ItemKind::Enum,
+    ItemKind::Function,
+];
+
+/// Whether items of `kind` are shown on a page of their own.
+pub fn has_page(kind: &ItemKind) -> bool {
+    PAGE_KINDS.contains(kind)
+}
+
+/// Whether `id` can be opened: it is the deprecated index or an item of `krate` with a page.
+pub fn opens(krate: &rdoc::Crate, id: &rdoc::Id) -> bool {
+    *id == deprecated_id()
+        || krate
+            .index
+            .get(id)
+            .map_or(false, |item| has_page(&item.kind))
+}
+
+/// The id of the deprecated items index, which isn't an item.
+pub fn deprecated_id() -> rdoc::Id {
+    rdoc::Id("druid-rustdoc:deprecated".into())
+}
+
+/// Marks `item` as public, for items that inherit their visibility like enum variants.
+fn inherited(mut item: data::Item) -> data::Item {
+    item.private = false;
+    item
+}
+
 fn item_to_data(item: &rdoc::Item, s: Option<&rdoc::ItemSummary>) -> data::Item {
     data::Item {
         name: item.name.clone().unwrap_or("_".into()),
         id: item.id.clone(),
+        visibility: item.visibility.clone(),
+        private: item.visibility != rdoc::Visibility::Public,
+        attrs: item
+            .attrs
+            .iter()
+            .filter_map(|a| attrs::relevant(a))
+            .collect(),
+        deprecation: item.deprecation.clone(),
+        cfg: Cfg::from_attrs(&item.attrs).map(Arc::new),
         parents: s
             .map(|s| s.path.iter().take(s.path.len() - 1).cloned().collect())
             .unwrap_or_default(),
@@ -258,26 +931,192 @@ fn item_to_data(item: &rdoc::Item, s: Option<&rdoc::ItemSummary>) -> data::Item
             .as_deref()
             .and_then(|doc| doc.lines().next())
             .map(Into::into),
-        doc: item.docs.clone().as_deref().map(markdown_to_text),
+        doc: item.docs.as_deref().map(|doc| data::Doc {
+            id: item.id.clone(),
+            markdown: doc.into(),
+        }),
+        // set for the whole page once it is built
+        paths: Arc::default(),
     }
 }
 
-impl AppDelegate<data::Screen> for Delegate {
+impl AppDelegate<data::App> for Delegate {
+    fn event(
+        &mut self,
+        ctx: &mut druid::DelegateCtx,
+        window_id: druid::WindowId,
+        event: Event,
+        _data: &mut data::App,
+        _env: &druid::Env,
+    ) -> Option<Event> {
+        if let Event::KeyDown(k) = &event {
+            if HotKey::new(SysMods::Cmd, "o").matches(k) {
+                ctx.submit_command(commands::SHOW_OPEN_PANEL.with(open_dialog()).to(window_id));
+                return None;
+            }
+        }
+        let pasted = match &event {
+            Event::Paste(clipboard) => pasted_crate(clipboard),
+            Event::KeyDown(k) if HotKey::new(SysMods::Cmd, "v").matches(k) => {
+                pasted_crate(&Application::global().clipboard())
+            }
+            _ => None,
+        };
+        if let Some(path) = pasted {
+            ctx.submit_command(OPEN_PATH.with(Arc::new(path)));
+            return None;
+        }
+        let back = match &event {
+            Event::MouseDown(m) => m.button == MouseButton::X1,
+            Event::KeyDown(k) => k.code == Code::ArrowLeft && k.mods.alt(),
+            _ => false,
+        };
+        if back {
+            ctx.submit_command(GO_BACK);
+            return None;
+        }
+        Some(event)
+    }
+
     fn command(
         &mut self,
         _ctx: &mut druid::DelegateCtx,
         _target: druid::Target,
         cmd: &druid::Command,
-        data: &mut data::Screen,
+        data: &mut data::App,
         _env: &druid::Env,
     ) -> druid::Handled {
         if let Some(link) = cmd.get(OPEN_LINK) {
             open::that_in_background(link);
         }
-        if let Some(id) = cmd.get(GOTO_ITEM) {
-            self.current = id.clone();
-            *data = self.data();
+        if let Some(file) = cmd.get(commands::OPEN_FILE) {
+            data.page = self.open(file.path().to_path_buf(), Settings::new(data));
+        }
+        if let Some(path) = cmd.get(OPEN_PATH) {
+            data.page = self.open(path.to_path_buf(), Settings::new(data));
+        }
+        let loaded = cmd
+            .get(LOADED)
+            .filter(|(generation, _)| *generation == self.generation)
+            .and_then(|(_, r)| r.take());
+        if let Some(result) = loaded {
+            match result {
+                Ok(loaded) => {
+                    let krate = loaded.docs.krate();
+                    self.add_recent(krate);
+                    md::clear_rendered();
+                    data.includes_private = krate.includes_private;
+                    data.features = loaded.features;
+                    data.page = data::Page::Docs(loaded.screen);
+                    self.docs = Some(loaded.docs);
+                }
+                Err(e) => {
+                    if let data::Page::Loading(loading) = &mut data.page {
+                        loading.error = Some(e.to_string());
+                    }
+                }
+            }
+        }
+        if let data::Page::Loading(loading) = &mut data.page {
+            if let Some((generation, line)) = cmd.get(BUILD_LOG) {
+                if *generation == self.generation {
+                    loading.log.push_back(line.clone());
+                }
+            }
+            if let Some((generation, progress)) = cmd.get(PROGRESS) {
+                if *generation == self.generation {
+                    loading.progress = Some(progress.clone());
+                }
+            }
+        }
+        // read before `docs` borrows the delegate
+        let current_generation = self.generation;
+        let docs = match &mut self.docs {
+            Some(docs) => docs,
+            None => return druid::Handled::No,
+        };
+        if let Some(action) = action(cmd) {
+            let mut settings = Settings::new(data);
+            if docs.apply(action, &mut settings) {
+                settings.store(data);
+                data.page = data::Page::Docs(docs.data());
+            }
+        }
+        if let Some(dir) = cmd.get(EXPORT_MARKDOWN) {
+            let current = docs.current().clone();
+            data.notification = Some(match export::write_markdown(docs, &current, dir.path()) {
+                Ok(written) => format!("Exported {} pages to {}", written, dir.path().display()),
+                Err(e) => format!("Export failed: {}", e),
+            });
+        }
+        let reloaded = cmd
+            .get(RELOAD)
+            .filter(|(generation, _)| *generation == current_generation)
+            .and_then(|(_, k)| k.take());
+        if let Some(krate) = reloaded {
+            docs.reload(krate);
+            let mut settings = Settings::new(data);
+            settings.load_features(docs);
+            docs.set_settings(&settings);
+            settings.store(data);
+            md::clear_rendered();
+            data.page = data::Page::Docs(docs.data());
+            data.notification = Some("Reloaded".into());
         }
         druid::Handled::No
     }
 }
+
+/// The action a command of the window stands for.
+fn action(cmd: &druid::Command) -> Option<Action> {
+    if let Some(id) = cmd.get(GOTO_ITEM) {
+        return Some(Action::Goto(id.clone()));
+    }
+    if let Some(name) = cmd.get(TOGGLE_FEATURE) {
+        return Some(Action::ToggleFeature(name.clone()));
+    }
+    let toggles = [
+        (GO_BACK, Action::Back),
+        (SHOW_DEPRECATED, Action::ShowDeprecated),
+        (TOGGLE_PRIVATE, Action::TogglePrivate),
+        (TOGGLE_FULL_PATHS, Action::ToggleFullPaths),
+        (CYCLE_ATTR_FILTER, Action::CycleAttrFilter),
+        (TOGGLE_DEPRECATED, Action::ToggleDeprecated),
+        (TOGGLE_FEATURE_FILTER, Action::ToggleFeatureFilter),
+    ];
+    toggles
+        .iter()
+        .find(|(selector, _)| cmd.is(*selector))
+        .map(|(_, action)| action.clone())
+}
+
+/// A crate without items in the current format, for tests to build on.
+#[cfg(test)]
+pub fn empty_crate() -> rdoc::Crate {
+    let json = include_str!("../rustdoc-types/tests/fixtures/empty.json");
+    rdoc::parse(json).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pasted_file_lists() {
+        let list = "# copied\r\nfile:///home/me/my%20crate/doc.json\r\nfile://host/srv/lib\r\n\r\n";
+        assert_eq!(
+            pasted_paths(list),
+            [
+                PathBuf::from("/home/me/my crate/doc.json"),
+                PathBuf::from("/srv/lib")
+            ]
+        );
+        // text only counts when it is an absolute path
+        assert_eq!(
+            pasted_paths("/tmp/a.json\nsrc\nhello world"),
+            [PathBuf::from("/tmp/a.json")]
+        );
+        assert_eq!(percent_decode("a%2Fb%zz%4"), "a/b%zz%4");
+        assert_eq!(percent_decode("%C3%A9"), "é");
+    }
+}
@@ -0,0 +1,857 @@
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+use druid::im::Vector;
+use pulldown_cmark::{html, Options, Parser};
+use rdoc::ItemKind;
+use rustdoc_types as rdoc;
+
+use crate::delegate::{deprecated_id, Docs};
+use crate::format::{
+    format_const, format_enum_decl, format_field, format_fn, format_impl_head, format_struct_decl,
+    format_type_def, format_variant, Code, PagePaths,
+};
+use crate::theme::{self, Syntax};
+use crate::{data, md};
+
+/// The width signatures are wrapped at, rustfmt's default.
+const WIDTH: usize = 100;
+
+/// Where the page of `id` goes in an export, as `/` separated segments ending in a file with
+/// `ext`. Modules get a directory with an index, other items a file named after their kind like
+/// rustdoc's. Items without a page of their own have none.
+pub fn page_path(krate: &rdoc::Crate, id: &rdoc::Id, ext: &str) -> Option<Vec<String>> {
+    if *id == deprecated_id() {
+        return Some(vec![format!("deprecated.{}", ext)]);
+    }
+    if *id == krate.root {
+        return Some(vec![format!("index.{}", ext)]);
+    }
+    let s = krate.paths.get(id).filter(|s| s.crate_id == 0)?;
+    // the first segment is the crate, which is the root of the export
+    let mut path: Vec<_> = s.path.iter().skip(1).cloned().collect();
+    let name = path.pop()?;
+    let file = match s.kind {
+        ItemKind::Module => {
+            path.push(name);
+            "index".to_string()
+        }
+        ItemKind::Struct => format!("struct.{}", name),
+        ItemKind::Enum => format!("enum.{}", name),
+        ItemKind::Function => format!("fn.{}", name),
+        _ => return None,
+    };
+    path.push(format!("{}.{}", file, ext));
+    Some(path)
+}
+
+/// The link from the page at `from` to the one at `to`, both as returned by [`page_path`].
+pub fn relative_link(from: &[String], to: &[String]) -> String {
+    let from_dir = &from[..from.len() - 1];
+    let common = from_dir.iter().zip(to).take_while(|(a, b)| a == b).count();
+    let mut link = "../".repeat(from_dir.len() - common);
+    link.push_str(&to[common..].join("/"));
+    link
+}
+
+/// `id` and, for modules, all items listed below it, in listing order.
+pub fn subtree(docs: &mut Docs, id: &rdoc::Id) -> Vec<rdoc::Id> {
+    let mut pages = Vec::new();
+    let mut stack = vec![id.clone()];
+    while let Some(id) = stack.pop() {
+        if let data::Screen::Mod(m) = docs.page(&id) {
+            let items = m
+                .mods
+                .iter()
+                .chain(&m.structs)
+                .chain(&m.enums)
+                .chain(&m.fns);
+            // reversed so they are popped in listing order
+            stack.extend(items.rev().map(|item| item.id.clone()));
+        }
+        pages.push(id);
+    }
+    pages
+}
+
+/// Renders the page of `id` as GitHub flavoured Markdown, links point to other pages as they
+/// are laid out by [`write_markdown`].
+pub fn markdown(docs: &mut Docs, id: &rdoc::Id) -> String {
+    let screen = docs.page(id);
+    render(docs.krate(), id, &screen, Markdown::default()).0
+}
+
+/// Writes the page of `id` and the pages below it into `dir`, returning how many were written.
+pub fn write_markdown(docs: &mut Docs, id: &rdoc::Id, dir: &Path) -> io::Result<usize> {
+    let mut written = 0;
+    for id in subtree(docs, id) {
+        if let Some(path) = page_path(docs.krate(), &id, "md") {
+            write_file(dir, &path, &markdown(docs, &id))?;
+            written += 1;
+        }
+    }
+    Ok(written)
+}
+
+/// Renders the page of `id` as an HTML document, using the stylesheet written by
+/// [`write_html`].
+pub fn html(docs: &mut Docs, id: &rdoc::Id) -> String {
+    let screen = docs.page(id);
+    let krate = docs.krate();
+    let path = page_path(krate, id, "html").unwrap_or_else(|| vec!["index.html".into()]);
+    let root = "../".repeat(path.len() - 1);
+    let crate_name = krate
+        .index
+        .get(&krate.root)
+        .and_then(|i| i.name.clone())
+        .unwrap_or_default();
+    let page = render(krate, id, &screen, Html::default());
+    format!(
+        "<!DOCTYPE html>\n\
+         <html lang=\"en\">\n\
+         <head>\n\
+         <meta charset=\"utf-8\">\n\
+         <title>{title}</title>\n\
+         <link rel=\"stylesheet\" href=\"{root}style.css\">\n\
+         </head>\n\
+         <body>\n\
+         <nav><a href=\"{root}index.html\">{krate}</a> \
+         <a href=\"{root}deprecated.html\">Deprecated items</a></nav>\n\
+         <main>\n{body}</main>\n\
+         </body>\n\
+         </html>\n",
+        title = escape(&page.title),
+        root = root,
+        krate = escape(&crate_name),
+        body = page.body,
+    )
+}
+
+/// Writes the whole crate into `dir` as a static site, one page per item and a stylesheet in
+/// the colors of the app. Returns how many pages were written.
+pub fn write_html(docs: &mut Docs, dir: &Path) -> io::Result<usize> {
+    let root = docs.krate().root.clone();
+    let mut pages = subtree(docs, &root);
+    pages.push(deprecated_id());
+    let mut written = 0;
+    for id in pages {
+        if let Some(path) = page_path(docs.krate(), &id, "html") {
+            write_file(dir, &path, &html(docs, &id))?;
+            written += 1;
+        }
+    }
+    write_file(dir, &["style.css".to_string()], &stylesheet())?;
+    Ok(written)
+}
+
+/// Runs the `markdown` command, printing the page of `item` or writing its subtree to `out`.
+pub fn markdown_command(
+    krate: rdoc::Crate,
+    item: Option<&str>,
+    out: Option<&Path>,
+) -> Result<(), String> {
+    let mut docs = Docs::new(krate);
+    let id = match item {
+        Some(path) => docs
+            .find(path)
+            .ok_or_else(|| format!("no item `{}` with a page", path))?,
+        None => docs.krate().root.clone(),
+    };
+    match out {
+        Some(dir) => {
+            let written = write_markdown(&mut docs, &id, dir)
+                .map_err(|e| format!("failed to write to {}: {}", dir.display(), e))?;
+            eprintln!("wrote {} pages to {}", written, dir.display());
+        }
+        None => print!("{}", markdown(&mut docs, &id)),
+    }
+    Ok(())
+}
+
+/// Runs the `export-html` command, writing the site of the crate to `out`.
+pub fn html_command(krate: rdoc::Crate, out: &Path) -> Result<(), String> {
+    let mut docs = Docs::new(krate);
+    let written = write_html(&mut docs, out)
+        .map_err(|e| format!("failed to write to {}: {}", out.display(), e))?;
+    eprintln!("wrote {} pages to {}", written, out.display());
+    Ok(())
+}
+
+fn write_file(dir: &Path, path: &[String], text: &str) -> io::Result<()> {
+    let file = path.iter().fold(dir.to_path_buf(), |file, s| file.join(s));
+    fs::create_dir_all(file.parent().unwrap())?;
+    fs::write(file, text)
+}
+
+/// Walks the page of `id`, writing it to `out`.
+pub fn render<O: Output>(krate: &rdoc::Crate, id: &rdoc::Id, screen: &data::Screen, out: O) -> O {
+    let ext = out.ext();
+    let paths = match screen {
+        data::Screen::Mod(m) => m.item.paths.clone(),
+        data::Screen::Struct(s) => s.item.paths.clone(),
+        data::Screen::Enum(e) => e.item.paths.clone(),
+        data::Screen::Fn(f) => f.item.paths.clone(),
+        data::Screen::Index(_) => Arc::default(),
+    };
+    let mut page = Page {
+        krate,
+        path: page_path(krate, id, ext).unwrap_or_else(|| vec![format!("index.{}", ext)]),
+        paths,
+        out,
+    };
+    match screen {
+        data::Screen::Mod(m) => page.mod_(m),
+        data::Screen::Struct(s) => page.struct_(s),
+        data::Screen::Enum(e) => page.enum_(e),
+        data::Screen::Fn(f) => page.fn_(f),
+        data::Screen::Index(i) => page.index(i),
+    }
+    page.out
+}
+
+/// A line of a listing.
+pub struct Entry<'a> {
+    pub id: rdoc::Id,
+    pub name: String,
+    pub link: Option<String>,
+    /// Badges after the name, like the features the item needs.
+    pub tags: Vec<String>,
+    /// Markdown after the name.
+    pub summary: Option<&'a str>,
+}
+
+/// The blocks pages are made of, written out in one format.
+pub trait Output {
+    /// The extension of the files, links point to pages with it.
+    fn ext(&self) -> &'static str;
+    /// The page title, `kind` is empty for pages that aren't an item.
+    fn title(&mut self, kind: &str, name: &str);
+    fn heading(&mut self, text: &str);
+    /// Formatted code, `links` point to the pages of the items named in it by their range.
+    fn code(&mut self, code: &Code, links: &[(Range<usize>, String)]);
+    /// A note like the deprecation of an item, `class` says which.
+    fn note(&mut self, markdown: &str, class: &str);
+    /// Docs with their links already pointing to the exported pages.
+    fn docs(&mut self, markdown: &str);
+    fn list(&mut self, entries: &[Entry]);
+}
+
+/// Walks a screen, writing it to `out`.
+struct Page<'a, O> {
+    krate: &'a rdoc::Crate,
+    /// Where the page goes, links are relative to it.
+    path: Vec<String>,
+    /// How types are named in the code on the page.
+    paths: Arc<PagePaths>,
+    out: O,
+}
+
+impl<O: Output> Page<'_, O> {
+    fn mod_(&mut self, m: &data::Mod) {
+        let kind = if m.item.id == self.krate.root {
+            "Crate"
+        } else {
+            "Module"
+        };
+        self.out.title(kind, &full_name(&m.item));
+        self.about(&m.item);
+        self.listing("Modules", &m.mods);
+        self.listing("Structs", &m.structs);
+        self.listing("Enums", &m.enums);
+        self.listing("Functions", &m.fns);
+    }
+
+    fn struct_(&mut self, s: &data::Struct) {
+        let kind = match s.struct_type {
+            rdoc::StructType::Union => "Union",
+            _ => "Struct",
+        };
+        self.out.title(kind, &full_name(&s.item));
+        self.code(|r| format_struct_decl(s, r));
+        self.about(&s.item);
+        if !s.fields.is_empty() {
+            self.out.heading("Fields");
+            for f in &s.fields {
+                self.code(|r| format_field(f, r));
+                self.about(&f.item);
+            }
+        }
+        self.impls("Implementations", &s.impls);
+        self.impls("Trait Implementations", &s.trait_impls);
+        self.impls("Auto Implementations", &s.auto_impls);
+    }
+
+    fn enum_(&mut self, e: &data::Enum) {
+        self.out.title("Enum", &full_name(&e.item));
+        self.code(|r| format_enum_decl(e, r));
+        self.about(&e.item);
+        if !e.variants.is_empty() {
+            self.out.heading("Variants");
+            for v in &e.variants {
+                self.code(|r| format_variant(v, r));
+                self.about(&v.item);
+            }
+        }
+        self.impls("Implementations", &e.impls);
+        self.impls("Trait Implementations", &e.trait_impls);
+        self.impls("Auto Implementations", &e.auto_impls);
+    }
+
+    fn fn_(&mut self, f: &data::Fn) {
+        self.out.title("Function", &full_name(&f.item));
+        self.code(|r| format_fn(f, false, WIDTH, r));
+        self.about(&f.item);
+    }
+
+    fn index(&mut self, i: &data::Index) {
+        self.out.title("", &i.title);
+        let notes: Vec<_> = i
+            .items
+            .iter()
+            .map(|item| item.deprecation.as_ref().map(md::deprecation_note))
+            .collect();
+        let entries: Vec<_> = i
+            .items
+            .iter()
+            .zip(&notes)
+            .map(|(item, note)| Entry {
+                id: item.id.clone(),
+                name: full_name(item),
+                link: self.link(&item.id),
+                tags: Vec::new(),
+                summary: note.as_deref(),
+            })
+            .collect();
+        self.out.list(&entries);
+    }
+
+    /// Adds a code block with what `f` formats.
+    fn code(&mut self, f: impl FnOnce(&mut Code)) {
+        let mut r = Code::with_paths(self.paths.clone(), false);
+        f(&mut r);
+        let links: Vec<_> = r
+            .links()
+            .iter()
+            .filter(|(range, _)| !range.is_empty())
+            .filter_map(|(range, id)| Some((range.clone(), self.link(id)?)))
+            .collect();
+        self.out.code(&r, &links);
+    }
+
+    /// Adds the deprecation and cfg notes and the docs of `item`.
+    fn about(&mut self, item: &data::Item) {
+        if let Some(d) = &item.deprecation {
+            self.out.note(&md::deprecation_note(d), "deprecated");
+        }
+        if let Some(cfg) = &item.cfg {
+            self.out.note(&cfg.note(), "cfg");
+        }
+        if let Some(doc) = &item.doc {
+            let text = match self.krate.index.get(&doc.id) {
+                Some(source) => self.rewrite_links(&doc.markdown, source),
+                None => doc.markdown.to_string(),
+            };
+            self.out.docs(&text);
+        }
+    }
+
+    fn listing(&mut self, head: &str, items: &Vector<data::Item>) {
+        if items.is_empty() {
+            return;
+        }
+        let entries: Vec<_> = items
+            .iter()
+            .map(|item| {
+                let mut tags = Vec::new();
+                if item.deprecation.is_some() {
+                    tags.push("Deprecated".to_string());
+                }
+                if let Some(cfg) = &item.cfg {
+                    tags.push(cfg.short());
+                }
+                Entry {
+                    id: item.id.clone(),
+                    name: item.name.clone(),
+                    link: self.link(&item.id),
+                    tags,
+                    summary: item.short_doc.as_deref(),
+                }
+            })
+            .collect();
+        self.out.heading(head);
+        self.out.list(&entries);
+    }
+
+    fn impls(&mut self, head: &str, impls: &Vector<data::Impl>) {
+        if impls.is_empty() {
+            return;
+        }
+        self.out.heading(head);
+        for i in impls {
+            self.code(|r| format_impl_head(i, r));
+            for t in &i.tys {
+                self.code(|r| format_type_def(t, r));
+                self.about(&t.item);
+            }
+            for c in &i.consts {
+                self.code(|r| format_const(c, r));
+                self.about(&c.item);
+            }
+            for f in &i.fns {
+                self.code(|r| format_fn(f, false, WIDTH, r));
+                self.about(&f.item);
+            }
+        }
+    }
+
+    /// The relative link to the page of `id`, if it has one.
+    fn link(&self, id: &rdoc::Id) -> Option<String> {
+        let to = page_path(self.krate, id, self.out.ext())?;
+        Some(relative_link(&self.path, &to))
+    }
+
+    /// Points the intra-doc links of `item` in `markdown` to the exported pages. Inline links
+    /// and reference definitions get the new destination, shortcut links like ``[`Vec`]`` get a
+    /// definition added. Links to items without a page keep only their text.
+    fn rewrite_links(&self, markdown: &str, item: &rdoc::Item) -> String {
+        let mut text = markdown.to_string();
+        let mut definitions = String::new();
+        let mut keys: Vec<_> = item.links.keys().collect();
+        keys.sort();
+        for key in keys {
+            let link = match self.link(&item.links[key]) {
+                Some(link) => link,
+                None => {
+                    text = unlink_key(&text, key);
+                    continue;
+                }
+            };
+            let inline = format!("]({})", key);
+            let mut rewritten = text.contains(&inline);
+            text = text.replace(&inline, &format!("]({})", link));
+            let lines: Vec<String> = text
+                .lines()
+                .map(|line| match line.split_once("]: ") {
+                    Some((label, dest)) if line.starts_with('[') && dest.trim() == key => {
+                        rewritten = true;
+                        format!("{}]: {}", label, link)
+                    }
+                    _ => line.to_string(),
+                })
+                .collect();
+            text = lines.join("\n");
+            if !rewritten {
+                definitions.push_str(&format!("\n[{}]: {}", key, link));
+            }
+        }
+        if !definitions.is_empty() {
+            text.push('\n');
+            text.push_str(&definitions);
+        }
+        text
+    }
+}
+
+/// Keeps only the text of the links to `key` in `markdown`, for keys without a page. Their
+/// reference definitions are dropped.
+fn unlink_key(markdown: &str, key: &str) -> String {
+    let mut labels = Vec::new();
+    let lines: Vec<&str> = markdown
+        .lines()
+        .filter(|line| match line.split_once("]: ") {
+            Some((label, dest)) if line.starts_with('[') && dest.trim() == key => {
+                labels.push(label[1..].to_string());
+                false
+            }
+            _ => true,
+        })
+        .collect();
+    let mut text = unlink(&lines.join("\n"), &format!("]({})", key));
+    for label in labels {
+        text = unlink(&text, &format!("][{}]", label));
+    }
+    text
+}
+
+/// Replaces the links ending in `end`, like `](Read)`, with their text.
+fn unlink(markdown: &str, end: &str) -> String {
+    let mut text = String::new();
+    let mut rest = markdown;
+    while let Some(i) = rest.find(end) {
+        let before = &rest[..i];
+        match opening_bracket(before) {
+            Some(open) => {
+                text.push_str(&before[..open]);
+                text.push_str(&before[open + 1..]);
+            }
+            None => {
+                text.push_str(before);
+                text.push_str(end);
+            }
+        }
+        rest = &rest[i + end.len()..];
+    }
+    text.push_str(rest);
+    text
+}
+
+/// The `[` opening the link text that `text` ends with, skipping nested brackets.
+fn opening_bracket(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text.char_indices().rev() {
+        match c {
+            ']' => depth += 1,
+            '[' if depth == 0 => return Some(i),
+            '[' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+#[derive(Default)]
+struct Markdown(String);
+
+impl Output for Markdown {
+    fn ext(&self) -> &'static str {
+        "md"
+    }
+
+    fn title(&mut self, kind: &str, name: &str) {
+        match kind {
+            "" => self.0.push_str(&format!("# {}\n\n", name)),
+            _ => self.0.push_str(&format!("# {} `{}`\n\n", kind, name)),
+        }
+    }
+
+    fn heading(&mut self, text: &str) {
+        self.0.push_str(&format!("## {}\n\n", text));
+    }
+
+    /// Fenced code blocks can't carry links, so the signature is written without them.
+    fn code(&mut self, code: &Code, _links: &[(Range<usize>, String)]) {
+        self.0.push_str("```rust\n");
+        self.0.push_str(code.as_str().trim_end());
+        self.0.push_str("\n```\n\n");
+    }
+
+    fn note(&mut self, markdown: &str, _class: &str) {
+        self.0.push_str(&format!("> {}\n\n", markdown));
+    }
+
+    fn docs(&mut self, markdown: &str) {
+        self.0.push_str(markdown.trim_end());
+        self.0.push_str("\n\n");
+    }
+
+    fn list(&mut self, entries: &[Entry]) {
+        for entry in entries {
+            match &entry.link {
+                Some(link) => self.0.push_str(&format!("- [`{}`]({})", entry.name, link)),
+                None => self.0.push_str(&format!("- `{}`", entry.name)),
+            }
+            for tag in &entry.tags {
+                self.0.push_str(&format!(" *{}*", tag));
+            }
+            if let Some(summary) = entry.summary {
+                self.0.push_str(" — ");
+                self.0.push_str(summary);
+            }
+            self.0.push('\n');
+        }
+        self.0.push('\n');
+    }
+}
+
+#[derive(Default)]
+struct Html {
+    title: String,
+    body: String,
+}
+
+impl Output for Html {
+    fn ext(&self) -> &'static str {
+        "html"
+    }
+
+    fn title(&mut self, kind: &str, name: &str) {
+        self.title = name.to_string();
+        match kind {
+            "" => self.body.push_str(&format!("<h1>{}</h1>\n", escape(name))),
+            _ => self.body.push_str(&format!(
+                "<h1>{} <code>{}</code></h1>\n",
+                kind,
+                escape(name)
+            )),
+        }
+    }
+
+    fn heading(&mut self, text: &str) {
+        self.body.push_str(&format!("<h2>{}</h2>\n", escape(text)));
+    }
+
+    fn code(&mut self, code: &Code, links: &[(Range<usize>, String)]) {
+        self.body.push_str("<pre class=\"code\">");
+        let mut start = 0;
+        for (text, syntax) in code.runs() {
+            let end = start + text.len();
+            // split the run where links start or end, so that tags nest
+            let mut cuts: Vec<_> = links
+                .iter()
+                .flat_map(|(range, _)| vec![range.start, range.end])
+                .filter(|&i| start < i && i < end)
+                .collect();
+            cuts.sort_unstable();
+            cuts.dedup();
+            cuts.push(end);
+            let mut from = start;
+            for to in cuts {
+                if let Some((_, link)) = links.iter().find(|(range, _)| range.start == from) {
+                    self.body
+                        .push_str(&format!("<a href=\"{}\">", escape(link)));
+                }
+                let text = escape(&code.as_str()[from..to]);
+                match syntax {
+                    Some(syntax) => self.body.push_str(&format!(
+                        "<span class=\"{}\">{}</span>",
+                        syntax.name(),
+                        text
+                    )),
+                    None => self.body.push_str(&text),
+                }
+                if links.iter().any(|(range, _)| range.end == to) {
+                    self.body.push_str("</a>");
+                }
+                from = to;
+            }
+            start = end;
+        }
+        self.body.push_str("</pre>\n");
+    }
+
+    fn note(&mut self, markdown: &str, class: &str) {
+        self.body
+            .push_str(&format!("<div class=\"note {}\">", class));
+        self.body.push_str(&markdown_to_html(markdown));
+        self.body.push_str("</div>\n");
+    }
+
+    fn docs(&mut self, markdown: &str) {
+        self.body.push_str("<div class=\"docs\">\n");
+        self.body.push_str(&markdown_to_html(markdown));
+        self.body.push_str("</div>\n");
+    }
+
+    fn list(&mut self, entries: &[Entry]) {
+        self.body.push_str("<ul class=\"items\">\n");
+        for entry in entries {
+            let name = format!("<code>{}</code>", escape(&entry.name));
+            self.body.push_str("<li>");
+            match &entry.link {
+                Some(link) => {
+                    self.body
+                        .push_str(&format!("<a href=\"{}\">{}</a>", escape(link), name))
+                }
+                None => self.body.push_str(&name),
+            }
+            for tag in &entry.tags {
+                self.body
+                    .push_str(&format!(" <span class=\"tag\">{}</span>", escape(tag)));
+            }
+            if let Some(summary) = entry.summary {
+                let summary = markdown_to_html(summary);
+                // summaries are a single paragraph, shown inline
+                let summary = summary
+                    .trim_end()
+                    .trim_start_matches("<p>")
+                    .trim_end_matches("</p>");
+                self.body
+                    .push_str(&format!(" <span class=\"summary\">{}</span>", summary));
+            }
+            self.body.push_str("</li>\n");
+        }
+        self.body.push_str("</ul>\n");
+    }
+}
+
+fn markdown_to_html(markdown: &str) -> String {
+    let mut out = String::new();
+    let options =
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    html::push_html(&mut out, Parser::new_ext(markdown, options));
+    out
+}
+
+/// The stylesheet of exported sites, with the colors of the app.
+fn stylesheet() -> String {
+    let mut css = format!(
+        "body {{ background: {background}; color: {text}; font-family: sans-serif; margin: 0; }}\n\
+         nav {{ padding: 10px; text-align: right; }}\n\
+         nav a {{ margin-left: 20px; color: {attr}; text-decoration: none; }}\n\
+         main {{ padding: 0 20px 20px; max-width: 1000px; }}\n\
+         a {{ color: {link}; }}\n\
+         h2 {{ border-bottom: 1px solid {attr}; }}\n\
+         code, pre {{ font-family: \"Source Code Pro\", monospace; }}\n\
+         pre.code {{ white-space: pre-wrap; }}\n\
+         .docs {{ margin-left: 20px; }}\n\
+         .note {{ border: 1px solid; border-radius: 4px; padding: 0 10px; }}\n\
+         .note.deprecated {{ border-color: {warning}; }}\n\
+         .note.cfg {{ border-color: {link}; }}\n\
+         ul.items {{ list-style: none; padding-left: 0; }}\n\
+         ul.items li {{ margin: 5px 0; }}\n\
+         .tag {{ font-size: 12px; color: {link}; border: 1px solid; border-radius: 4px; padding: 0 4px; }}\n\
+         .summary {{ margin-left: 20px; }}\n",
+        background = css_color(theme::BACKGROUND_RGBA),
+        text = css_color(theme::TEXT_RGBA),
+        attr = css_color(Syntax::Attr.rgba()),
+        link = css_color(Syntax::Trait.rgba()),
+        warning = css_color(theme::WARNING_RGBA),
+    );
+    for syntax in Syntax::ALL {
+        css.push_str(&format!(
+            ".code .{} {{ color: {}; }}\n",
+            syntax.name(),
+            css_color(syntax.rgba())
+        ));
+    }
+    css
+}
+
+fn css_color(rgba: u32) -> String {
+    format!("#{:08x}", rgba)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// `item` with the path of its parents, like `krate::module::Item`.
+fn full_name(item: &data::Item) -> String {
+    let mut name: Vec<_> = item.parents.iter().cloned().collect();
+    name.push(item.name.clone());
+    name.join("::")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::delegate::empty_crate;
+
+    fn id(id: &str) -> rdoc::Id {
+        rdoc::Id(id.into())
+    }
+
+    fn segments(path: &[&str]) -> Vec<String> {
+        path.iter().map(|s| s.to_string()).collect()
+    }
+
+    /// The crate `demo` with the module `io`, the struct `io::File`, the function `open` and the
+    /// trait `Read`, and a struct of another crate.
+    fn krate() -> rdoc::Crate {
+        let summary = |crate_id, path: &[&str], kind| rdoc::ItemSummary {
+            crate_id,
+            path: path.iter().map(|s| s.to_string()).collect(),
+            kind,
+        };
+        let paths = vec![
+            (id("0"), summary(0, &["demo"], ItemKind::Module)),
+            (id("1"), summary(0, &["demo", "io"], ItemKind::Module)),
+            (
+                id("2"),
+                summary(0, &["demo", "io", "File"], ItemKind::Struct),
+            ),
+            (id("3"), summary(0, &["demo", "open"], ItemKind::Function)),
+            (id("4"), summary(0, &["demo", "Read"], ItemKind::Trait)),
+            (
+                id("5"),
+                summary(1, &["std", "string", "String"], ItemKind::Struct),
+            ),
+        ]
+        .into_iter()
+        .collect();
+        rdoc::Crate {
+            root: id("0"),
+            paths,
+            ..empty_crate()
+        }
+    }
+
+    #[test]
+    fn page_paths() {
+        let krate = krate();
+        let path = |i: &str| page_path(&krate, &id(i), "md");
+        assert_eq!(path("0"), Some(segments(&["index.md"])));
+        assert_eq!(path("1"), Some(segments(&["io", "index.md"])));
+        assert_eq!(path("2"), Some(segments(&["io", "struct.File.md"])));
+        assert_eq!(path("3"), Some(segments(&["fn.open.md"])));
+        assert_eq!(
+            page_path(&krate, &deprecated_id(), "html"),
+            Some(segments(&["deprecated.html"]))
+        );
+        // traits have no page, items of other crates aren't exported
+        assert_eq!(path("4"), None);
+        assert_eq!(path("5"), None);
+    }
+
+    #[test]
+    fn relative_links() {
+        let link = |from: &[&str], to: &[&str]| relative_link(&segments(from), &segments(to));
+        assert_eq!(link(&["index.md"], &["io", "index.md"]), "io/index.md");
+        assert_eq!(
+            link(&["io", "struct.File.md"], &["fn.open.md"]),
+            "../fn.open.md"
+        );
+        assert_eq!(
+            link(&["io", "struct.File.md"], &["io", "index.md"]),
+            "index.md"
+        );
+        assert_eq!(link(&["a", "b", "x.md"], &["a", "c", "y.md"]), "../c/y.md");
+    }
+
+    #[test]
+    fn rewrites_intra_doc_links() {
+        let krate = krate();
+        let page = Page {
+            krate: &krate,
+            path: segments(&["index.md"]),
+            paths: Arc::default(),
+            out: Markdown::default(),
+        };
+        let item = rdoc::Item {
+            id: id("0"),
+            crate_id: 0,
+            name: Some("demo".into()),
+            source: None,
+            visibility: rdoc::Visibility::Public,
+            docs: None,
+            links: vec![
+                ("`File`".to_string(), id("2")),
+                ("open".to_string(), id("3")),
+                ("Read".to_string(), id("4")),
+            ]
+            .into_iter()
+            .collect(),
+            attrs: Vector::new(),
+            deprecation: None,
+            kind: ItemKind::StructField,
+            inner: rdoc::ItemEnum::StructFieldItem(rdoc::Type::Primitive("u8".into())),
+        };
+        let markdown =
+            "See [`File`], [opening](open) and [reading](Read), or [[the] trait][r].\n\n\
+             [ref]: open\n\
+             [r]: Read";
+        // traits have no page, their links keep only the text
+        assert_eq!(
+            page.rewrite_links(markdown, &item),
+            "See [`File`], [opening](fn.open.md) and reading, or [the] trait.\n\n\
+             [ref]: fn.open.md\n\n\
+             [`File`]: io/struct.File.md"
+        );
+    }
+}
@@ -1,46 +1,214 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::sync::Arc;
+
 use druid::im::Vector;
-use druid::text::RichTextBuilder;
+use druid::text::{RichText, RichTextBuilder};
 use rustdoc_types::{
     FnDecl, GenericArg, GenericArgs, GenericBound, GenericParamDef, GenericParamDefKind, Generics,
-    Qualifiers, TraitBoundModifier, Type, TypeBindingKind, WherePredicate,
+    Id, Qualifiers, StructType, TraitBoundModifier, Type, TypeBindingKind, Visibility,
+    WherePredicate,
 };
 
-use crate::theme;
+use crate::data;
+use crate::theme::Syntax;
+
+/// Formatted code with the syntax of its spans, built into `RichText` for the UI and turned into
+/// HTML or terminal colors by exports.
+#[derive(Debug, Default, Clone)]
+pub struct Code {
+    text: String,
+    spans: Vec<(Range<usize>, Syntax)>,
+    /// Spans naming an item, which open it when clicked.
+    links: Vec<(Range<usize>, Id)>,
+    paths: Arc<PagePaths>,
+    /// Full paths regardless of `paths`, e.g. while a label is hovered.
+    full_paths: bool,
+}
+
+/// The text just pushed to a [`Code`], to give it a color or a link.
+pub struct Span<'a> {
+    code: &'a mut Code,
+    range: Range<usize>,
+}
+
+impl Span<'_> {
+    pub fn color(self, syntax: Syntax) -> Self {
+        self.code.spans.push((self.range.clone(), syntax));
+        self
+    }
+
+    pub fn link(self, id: Id) -> Self {
+        self.code.links.push((self.range.clone(), id));
+        self
+    }
+}
+
+impl Code {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Code naming types the way the page with `paths` does, or by their full paths when `full`.
+    pub fn with_paths(paths: Arc<PagePaths>, full: bool) -> Self {
+        Self {
+            paths,
+            full_paths: full,
+            ..Self::default()
+        }
+    }
+
+    pub fn push(&mut self, text: &str) -> Span<'_> {
+        let start = self.text.len();
+        self.text.push_str(text);
+        let range = start..self.text.len();
+        Span { code: self, range }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// The number of characters `f` formats, naming types like this code.
+    fn text_len(&self, f: impl FnOnce(&mut Code)) -> usize {
+        let mut r = Code::with_paths(self.paths.clone(), self.full_paths);
+        f(&mut r);
+        r.as_str().chars().count()
+    }
+
+    /// The name to show for the path `name` to `id`.
+    fn path_name(&self, id: &Id, name: &str) -> String {
+        let p = &self.paths;
+        if self.full_paths || p.show_full || p.ambiguous.contains(id) {
+            p.full.get(id).cloned().unwrap_or_else(|| name.to_string())
+        } else {
+            name.rsplit("::").next().unwrap().to_string()
+        }
+    }
+
+    /// The text in runs of the same syntax, `None` for plain text.
+    pub fn runs(&self) -> Vec<(&str, Option<Syntax>)> {
+        let mut runs = Vec::new();
+        let mut end = 0;
+        for (range, syntax) in &self.spans {
+            if range.start > end {
+                runs.push((&self.text[end..range.start], None));
+            }
+            runs.push((&self.text[range.clone()], Some(*syntax)));
+            end = range.end;
+        }
+        if end < self.text.len() {
+            runs.push((&self.text[end..], None));
+        }
+        runs
+    }
+
+    /// The items named, by the range of their name in [`Code::as_str`].
+    pub fn links(&self) -> &[(Range<usize>, Id)] {
+        &self.links
+    }
+
+    pub fn build(&self) -> RichText {
+        let mut r = RichTextBuilder::new();
+        for (text, syntax) in self.runs() {
+            let attrs = r.push(text);
+            if let Some(syntax) = syntax {
+                attrs.text_color(syntax.key());
+            }
+        }
+        let links = self
+            .links
+            .iter()
+            .filter(|(_, id)| self.paths.pages.contains(id));
+        for (range, id) in links {
+            r.add_attributes_for_range(range.clone())
+                .link(super::GOTO_ITEM.with(id.clone()));
+        }
+        r.build()
+    }
+}
+
+/// How the names of `Type::ResolvedPath`s are shown on a page: the last segment, unless full
+/// paths are wanted or the short name is ambiguous on the page.
+#[derive(Debug, Default, Clone)]
+pub struct PagePaths {
+    /// Full paths everywhere, toggled from the toolbar.
+    pub show_full: bool,
+    /// The full paths of the types on the page.
+    pub full: HashMap<Id, String>,
+    /// The types sharing their short name with another type on the page.
+    pub ambiguous: HashSet<Id>,
+    /// The types on the page that have a page of their own, only these are linked.
+    pub pages: HashSet<Id>,
+}
 
-pub fn format_ty(ty: &Type, hint_trait: bool, r: &mut RichTextBuilder) {
+pub fn format_ty(ty: &Type, hint_trait: bool, r: &mut Code) {
     match ty {
         Type::ResolvedPath {
             name,
             id,
             args,
-            param_names: _,
+            param_names,
         } => {
-            let name = name.rsplit("::").next().unwrap();
-            r.push(name)
-                .text_color(if hint_trait {
-                    theme::TRAIT_COLOR
+            // older formats encode `dyn Trait + Send` as the principal trait with the other
+            // bounds in `param_names`
+            let is_dyn = !param_names.is_empty();
+            if is_dyn {
+                r.push("dyn ");
+            }
+            let name = r.path_name(id, name);
+            r.push(&name)
+                .color(if hint_trait || is_dyn {
+                    Syntax::Trait
                 } else {
-                    theme::STRUCT_COLOR
+                    Syntax::Struct
                 })
-                .link(super::GOTO_ITEM.with(id.clone()));
+                .link(id.clone());
 
             if let Some(args) = args {
                 format_generic_args(args, r);
             }
+            if is_dyn {
+                r.push(" + ");
+                format_generic_bound(param_names, r);
+            }
+        }
+        Type::DynTrait(dt) => {
+            r.push("dyn ");
+            format_seperated(dt.traits.iter(), " + ", r, |pt, r| {
+                format_hrtb(&pt.generic_params, r);
+                format_ty(&pt.trait_, true, r);
+            });
+            if let Some(lf) = &dt.lifetime {
+                r.push(" + ");
+                r.push(lf);
+            }
         }
         Type::Generic(g) => {
-            r.push(g).text_color(theme::TYPE_COLOR);
+            r.push(g).color(Syntax::Type);
         }
         Type::Primitive(p) => {
-            r.push(p).text_color(theme::PRIMITIVE_COLOR);
+            r.push(p).color(Syntax::Primitive);
         }
         Type::FunctionPointer(f) => {
-            r.push("(");
+            format_hrtb(&f.generic_params, r);
+            format_qualifiers(&f.header, &f.abi, r);
+            r.push("fn(");
             format_seperated(f.decl.inputs.iter(), ", ", r, |(name, ty), r| {
-                r.push(name);
-                r.push(": ");
+                // names of function pointer arguments are optional
+                if !name.is_empty() && name != "_" {
+                    r.push(name);
+                    r.push(": ");
+                }
                 format_ty(ty, false, r);
             });
+            if f.decl.c_variadic {
+                r.push(if f.decl.inputs.is_empty() {
+                    "..."
+                } else {
+                    ", ..."
+                });
+            }
             r.push(")");
             if let Some(ty) = &f.decl.output {
                 r.push(" -> ");
@@ -77,7 +245,7 @@ pub fn format_ty(ty: &Type, hint_trait: bool, r: &mut RichTextBuilder) {
             } else {
                 r.push("const ");
             }
-            format_ty(type_, false, r);
+            format_pointee(type_, r);
         }
         Type::BorrowedRef {
             lifetime,
@@ -92,7 +260,7 @@ pub fn format_ty(ty: &Type, hint_trait: bool, r: &mut RichTextBuilder) {
             if *mutable {
                 r.push("mut ");
             }
-            format_ty(type_, false, r);
+            format_pointee(type_, r);
         }
         Type::QualifiedPath {
             name,
@@ -112,7 +280,7 @@ pub fn format_ty(ty: &Type, hint_trait: bool, r: &mut RichTextBuilder) {
                     r.push(">::");
                 }
             }
-            r.push(name).text_color(theme::TYPE_COLOR);
+            r.push(name).color(Syntax::Type);
         }
         Type::ImplTrait(tr) => {
             r.push("impl ");
@@ -121,17 +289,43 @@ pub fn format_ty(ty: &Type, hint_trait: bool, r: &mut RichTextBuilder) {
     }
 }
 
+/// Formats the type behind a reference or pointer, `&(dyn A + B)` needs parentheses.
+fn format_pointee(ty: &Type, r: &mut Code) {
+    let multiple_bounds = match ty {
+        Type::ResolvedPath { param_names, .. } => !param_names.is_empty(),
+        Type::DynTrait(dt) => dt.traits.len() > 1 || dt.lifetime.is_some(),
+        Type::ImplTrait(bounds) => bounds.len() > 1,
+        _ => false,
+    };
+    if multiple_bounds {
+        r.push("(");
+        format_ty(ty, false, r);
+        r.push(")");
+    } else {
+        format_ty(ty, false, r);
+    }
+}
+
+/// Formats the `for<'a> ` of a higher-ranked bound, if any.
+fn format_hrtb(params: &Vector<GenericParamDef>, r: &mut Code) {
+    if !params.is_empty() {
+        r.push("for<");
+        format_generics_def(params, false, r);
+        r.push("> ");
+    }
+}
+
 pub fn format_generics_def<'a>(
     g: impl IntoIterator<Item = &'a GenericParamDef>,
     no_bounds: bool,
-    r: &'a mut RichTextBuilder,
+    r: &'a mut Code,
 ) {
     format_seperated(g.into_iter(), ", ", r, |g, r| match &g.kind {
         GenericParamDefKind::Lifetime => {
             r.push(&g.name);
         }
         GenericParamDefKind::Type { bounds, default } => {
-            r.push(&g.name).text_color(theme::TYPE_COLOR);
+            r.push(&g.name).color(Syntax::Type);
             if !bounds.is_empty() && !no_bounds {
                 r.push(": ");
                 format_generic_bound(bounds, r);
@@ -143,17 +337,14 @@ pub fn format_generics_def<'a>(
         }
         GenericParamDefKind::Const(c) => {
             r.push("const ");
-            r.push(&g.name).text_color(theme::TYPE_COLOR);
+            r.push(&g.name).color(Syntax::Type);
             r.push(": ");
             format_ty(c, false, r);
         }
     });
 }
 
-pub fn format_generic_bound<'a>(
-    g: impl IntoIterator<Item = &'a GenericBound>,
-    r: &'a mut RichTextBuilder,
-) {
+pub fn format_generic_bound<'a>(g: impl IntoIterator<Item = &'a GenericBound>, r: &'a mut Code) {
     format_seperated(g.into_iter(), " + ", r, |g, r| {
         match g {
             GenericBound::TraitBound {
@@ -161,18 +352,14 @@ pub fn format_generic_bound<'a>(
                 generic_params,
                 modifier,
             } => {
-                if !generic_params.is_empty() {
-                    r.push("for<");
-                    format_generics_def(generic_params, false, r);
-                    r.push("> ");
-                }
+                format_hrtb(generic_params, r);
                 match modifier {
                     TraitBoundModifier::None => {}
                     TraitBoundModifier::Maybe => {
                         r.push("?");
                     }
                     TraitBoundModifier::MaybeConst => {
-                        r.push("?const ");
+                        r.push("~const ");
                     }
                 }
                 format_ty(trait_, true, r);
@@ -184,7 +371,7 @@ pub fn format_generic_bound<'a>(
     });
 }
 
-pub fn format_generic_args(g: &GenericArgs, r: &mut RichTextBuilder) {
+pub fn format_generic_args(g: &GenericArgs, r: &mut Code) {
     match g {
         GenericArgs::AngleBracketed { args, bindings }
             if !args.is_empty() || !bindings.is_empty() =>
@@ -199,7 +386,7 @@ pub fn format_generic_args(g: &GenericArgs, r: &mut RichTextBuilder) {
                 }
                 GenericArg::Const(c) => {
                     r.push("{");
-                    r.push(c.value.as_ref().unwrap());
+                    r.push(c.value.as_ref().unwrap_or(&c.expr));
                     r.push("}");
                 }
             });
@@ -207,7 +394,7 @@ pub fn format_generic_args(g: &GenericArgs, r: &mut RichTextBuilder) {
                 r.push(", ");
             }
             format_seperated(bindings.iter(), ", ", r, |a, r| {
-                r.push(&a.name).text_color(theme::TYPE_COLOR);
+                r.push(&a.name).color(Syntax::Type);
                 match &a.binding {
                     TypeBindingKind::Equality(ty) => {
                         r.push(" = ");
@@ -234,132 +421,550 @@ pub fn format_generic_args(g: &GenericArgs, r: &mut RichTextBuilder) {
     }
 }
 
+/// Formats a where clause with one predicate per line. Like rustfmt, the last predicate only
+/// gets a comma when a body follows, a `;` directly ends it.
 pub fn format_wheres<'a>(
     others: impl IntoIterator<Item = &'a GenericParamDef>,
     wheres: impl IntoIterator<Item = &'a WherePredicate>,
-    r: &mut RichTextBuilder,
+    trailing_comma: bool,
+    r: &mut Code,
 ) {
     r.push("where");
+    let mut is_first = true;
+    let mut next_line = |r: &mut Code| {
+        if !is_first {
+            r.push(",");
+        }
+        is_first = false;
+        r.push("\n    ");
+    };
     for i in others {
         if i.name.starts_with("impl ") {
             continue;
         }
         match &i.kind {
             GenericParamDefKind::Type { bounds, default: _ } if !bounds.is_empty() => {
-                r.push("\n    ");
-                r.push(&i.name).text_color(theme::TYPE_COLOR);
+                next_line(r);
+                r.push(&i.name).color(Syntax::Type);
                 r.push(": ");
                 format_generic_bound(bounds, r);
-                r.push(",");
             }
             _ => {}
         }
     }
     for i in wheres {
         match i {
-            WherePredicate::BoundPredicate { ty, bounds } => {
-                r.push("\n    ");
+            WherePredicate::BoundPredicate {
+                ty,
+                bounds,
+                generic_params,
+            } => {
+                next_line(r);
+                format_hrtb(generic_params, r);
                 format_ty(ty, false, r);
                 r.push(": ");
                 format_generic_bound(bounds, r);
-                r.push(",");
             }
             WherePredicate::RegionPredicate { lifetime, bounds } => {
-                r.push("\n    ");
+                next_line(r);
                 r.push(lifetime);
                 r.push(": ");
                 format_generic_bound(bounds, r);
-                r.push(", ");
             }
-            WherePredicate::EqPredicate { lhs: _, rhs: _ } => {}
+            WherePredicate::EqPredicate { lhs, rhs } => {
+                next_line(r);
+                format_ty(lhs, false, r);
+                r.push(" == ");
+                format_ty(rhs, false, r);
+            }
         }
     }
+    if trailing_comma && !is_first {
+        r.push(",");
+    }
 }
-pub fn format_fn(
-    name: &str,
-    header: &Vector<Qualifiers>,
-    gens: &Generics,
-    decl: &FnDecl,
-    r: &mut RichTextBuilder,
-) {
-    r.push("pub fn ");
-    for h in header {
-        match h {
-            Qualifiers::Const => {
-                r.push("const ");
+
+/// Formats the declaration of `s` like rustdoc shows it, with a note for hidden fields.
+pub fn format_struct_decl(s: &data::Struct, r: &mut Code) {
+    format_attrs(&s.item.attrs, r);
+    format_visibility(&s.item.visibility, r);
+    r.push(match s.struct_type {
+        StructType::Union => "union ",
+        _ => "struct ",
+    });
+    r.push(&s.item.name).color(Syntax::Struct);
+    format_decl_generics(&s.generics, r);
+    match s.struct_type {
+        StructType::Unit => {
+            format_decl_wheres(&s.generics, false, r);
+            r.push(";");
+        }
+        StructType::Tuple => {
+            r.push("(");
+            format_seperated(s.fields.iter(), ", ", r, |f, r| {
+                format_visibility(&f.item.visibility, r);
+                format_ty(&f.ty, false, r);
+            });
+            if s.fields_stripped {
+                if !s.fields.is_empty() {
+                    r.push(", ");
+                }
+                r.push("/* private fields */");
+            }
+            r.push(")");
+            format_decl_wheres(&s.generics, false, r);
+            r.push(";");
+        }
+        StructType::Plain | StructType::Union => {
+            format_decl_wheres(&s.generics, true, r);
+            format_open_brace(&s.generics, r);
+            for f in &s.fields {
+                r.push("\n    ");
+                format_visibility(&f.item.visibility, r);
+                r.push(&f.item.name);
+                r.push(": ");
+                format_ty(&f.ty, false, r);
+                r.push(",");
             }
-            Qualifiers::Unsafe => {
-                r.push("unsafe ");
+            if s.fields_stripped {
+                r.push("\n    /* private fields */");
             }
-            Qualifiers::Async => {
-                r.push("async ");
+            if !s.fields.is_empty() || s.fields_stripped {
+                r.push("\n");
             }
-            _ => {}
-        };
+            r.push("}");
+        }
+    }
+}
+
+/// Formats the declaration of `e` with the payloads of its variants.
+pub fn format_enum_decl(e: &data::Enum, r: &mut Code) {
+    format_attrs(&e.item.attrs, r);
+    format_visibility(&e.item.visibility, r);
+    r.push("enum ");
+    r.push(&e.item.name).color(Syntax::Enum);
+    format_decl_generics(&e.generics, r);
+    format_decl_wheres(&e.generics, true, r);
+    format_open_brace(&e.generics, r);
+    for v in &e.variants {
+        r.push("\n    ");
+        r.push(&v.item.name).color(Syntax::Enum);
+        match &v.inner {
+            data::VariantInner::Plain => {}
+            data::VariantInner::Tuple(ts) => {
+                r.push("(");
+                format_seperated(ts.iter(), ", ", r, |ty, r| format_ty(ty, false, r));
+                r.push(")");
+            }
+            data::VariantInner::Struct(fs) => {
+                r.push(" { ");
+                format_seperated(fs.iter(), ", ", r, |f, r| {
+                    r.push(&f.item.name);
+                    r.push(": ");
+                    format_ty(&f.ty, false, r);
+                });
+                r.push(" }");
+            }
+        }
+        r.push(",");
+    }
+    if e.variants_stripped {
+        r.push("\n    // some variants omitted");
+    }
+    if !e.variants.is_empty() || e.variants_stripped {
+        r.push("\n");
+    }
+    r.push("}");
+}
+
+pub fn format_field(f: &data::Field, r: &mut Code) {
+    format_visibility(&f.item.visibility, r);
+    r.push(&f.item.name);
+    r.push(": ");
+    format_ty(&f.ty, false, r);
+}
+
+/// Formats `v` with its payload, struct variants get a field per line.
+pub fn format_variant(v: &data::Variant, r: &mut Code) {
+    r.push(&v.item.name).color(Syntax::Enum);
+    match &v.inner {
+        data::VariantInner::Plain => {}
+        data::VariantInner::Tuple(ts) => {
+            r.push("(");
+            format_seperated(ts.iter(), ", ", r, |ty, r| {
+                format_ty(ty, false, r);
+            });
+            r.push(")");
+        }
+        data::VariantInner::Struct(fs) => {
+            if !fs.is_empty() {
+                r.push(" {");
+                r.push("\n");
+            } else {
+                r.push("{ ");
+            }
+            for f in fs.iter() {
+                r.push("    ");
+                r.push(&f.item.name);
+                r.push(": ");
+                format_ty(&f.ty, false, r);
+                r.push(",");
+                r.push("\n");
+            }
+            r.push("}");
+        }
+    }
+}
+
+/// Formats `impl<..> Trait for Type` with the where clause on the following lines.
+pub fn format_impl_head(i: &data::Impl, r: &mut Code) {
+    r.push("impl");
+    if !i.generics.params.is_empty() {
+        r.push("<");
+        format_generics_def(&i.generics.params, true, r);
+        r.push(">");
+    }
+
+    r.push(" ");
+    if let Some(tr) = &i.trait_ {
+        format_ty(tr, true, r);
+        r.push(" for ");
+    }
+    format_ty(&i.for_, false, r);
+    if has_wheres(&i.generics) {
+        r.push("\n");
+        format_wheres(&i.generics.params, &i.generics.where_predicates, true, r);
+    }
+}
+
+pub fn format_const(c: &data::Const, r: &mut Code) {
+    format_visibility(&c.item.visibility, r);
+    r.push("const ");
+    r.push(&c.item.name).color(Syntax::Const);
+    r.push(": ");
+    format_ty(&c.ty, false, r);
+}
+
+pub fn format_type_def(t: &data::TypeDef, r: &mut Code) {
+    format_visibility(&t.item.visibility, r);
+    r.push("type ");
+    r.push(&t.item.name).color(Syntax::Type);
+    r.push(" = ");
+    format_ty(&t.ty, false, r);
+}
+
+/// Formats each attribute on its own line.
+fn format_attrs(attrs: &Vector<String>, r: &mut Code) {
+    for attr in attrs {
+        r.push(attr).color(Syntax::Attr);
+        r.push("\n");
+    }
+}
+
+/// Formats the generic parameters of a declaration, their bounds go into the where clause.
+fn format_decl_generics(g: &Generics, r: &mut Code) {
+    if !g.params.is_empty() {
+        r.push("<");
+        format_generics_def(&g.params, true, r);
+        r.push(">");
+    }
+}
+
+fn format_decl_wheres(g: &Generics, trailing_comma: bool, r: &mut Code) {
+    if has_wheres(g) {
+        r.push("\n");
+        format_wheres(&g.params, &g.where_predicates, trailing_comma, r);
+    }
+}
+
+/// Opens a body, on its own line after a where clause like rustfmt does.
+fn format_open_brace(g: &Generics, r: &mut Code) {
+    if has_wheres(g) {
+        r.push("\n{");
+    } else {
+        r.push(" {");
+    }
+}
+
+/// Calls `f` with every path in `ty`.
+pub fn visit_ty_paths(ty: &Type, f: &mut dyn FnMut(&Id, &str)) {
+    match ty {
+        Type::ResolvedPath {
+            name,
+            id,
+            args,
+            param_names,
+        } => {
+            f(id, name);
+            if let Some(args) = args {
+                visit_args_paths(args, f);
+            }
+            visit_bounds_paths(param_names, f);
+        }
+        Type::DynTrait(dt) => {
+            for pt in &dt.traits {
+                visit_params_paths(&pt.generic_params, f);
+                visit_ty_paths(&pt.trait_, f);
+            }
+        }
+        Type::FunctionPointer(fp) => {
+            visit_params_paths(&fp.generic_params, f);
+            visit_decl_paths(&fp.decl, f);
+        }
+        Type::Tuple(ts) => ts.iter().for_each(|ty| visit_ty_paths(ty, f)),
+        Type::Slice(ty)
+        | Type::Array { type_: ty, .. }
+        | Type::RawPointer { type_: ty, .. }
+        | Type::BorrowedRef { type_: ty, .. } => visit_ty_paths(ty, f),
+        Type::QualifiedPath {
+            self_type, trait_, ..
+        } => {
+            visit_ty_paths(self_type, f);
+            visit_ty_paths(trait_, f);
+        }
+        Type::ImplTrait(bounds) => visit_bounds_paths(bounds, f),
+        Type::Generic(_) | Type::Primitive(_) | Type::Never | Type::Infer => {}
+    }
+}
+
+pub fn visit_generics_paths(g: &Generics, f: &mut dyn FnMut(&Id, &str)) {
+    visit_params_paths(&g.params, f);
+    for p in &g.where_predicates {
+        match p {
+            WherePredicate::BoundPredicate {
+                ty,
+                bounds,
+                generic_params,
+            } => {
+                visit_params_paths(generic_params, f);
+                visit_ty_paths(ty, f);
+                visit_bounds_paths(bounds, f);
+            }
+            WherePredicate::RegionPredicate { bounds, .. } => visit_bounds_paths(bounds, f),
+            WherePredicate::EqPredicate { lhs, rhs } => {
+                visit_ty_paths(lhs, f);
+                visit_ty_paths(rhs, f);
+            }
+        }
+    }
+}
+
+pub fn visit_decl_paths(decl: &FnDecl, f: &mut dyn FnMut(&Id, &str)) {
+    for (_, ty) in &decl.inputs {
+        visit_ty_paths(ty, f);
+    }
+    if let Some(ty) = &decl.output {
+        visit_ty_paths(ty, f);
+    }
+}
+
+fn visit_params_paths(params: &Vector<GenericParamDef>, f: &mut dyn FnMut(&Id, &str)) {
+    for p in params {
+        match &p.kind {
+            GenericParamDefKind::Lifetime => {}
+            GenericParamDefKind::Type { bounds, default } => {
+                visit_bounds_paths(bounds, f);
+                if let Some(ty) = default {
+                    visit_ty_paths(ty, f);
+                }
+            }
+            GenericParamDefKind::Const(ty) => visit_ty_paths(ty, f),
+        }
+    }
+}
+
+fn visit_bounds_paths(bounds: &Vector<GenericBound>, f: &mut dyn FnMut(&Id, &str)) {
+    for b in bounds {
+        if let GenericBound::TraitBound {
+            trait_,
+            generic_params,
+            ..
+        } = b
+        {
+            visit_params_paths(generic_params, f);
+            visit_ty_paths(trait_, f);
+        }
+    }
+}
+
+fn visit_args_paths(args: &GenericArgs, f: &mut dyn FnMut(&Id, &str)) {
+    match args {
+        GenericArgs::AngleBracketed { args, bindings } => {
+            for a in args {
+                if let GenericArg::Type(ty) = a {
+                    visit_ty_paths(ty, f);
+                }
+            }
+            for b in bindings {
+                match &b.binding {
+                    TypeBindingKind::Equality(ty) => visit_ty_paths(ty, f),
+                    TypeBindingKind::Constraint(bounds) => visit_bounds_paths(bounds, f),
+                }
+            }
+        }
+        GenericArgs::Parenthesized { inputs, output } => {
+            inputs.iter().for_each(|ty| visit_ty_paths(ty, f));
+            if let Some(ty) = output {
+                visit_ty_paths(ty, f);
+            }
+        }
+    }
+}
+
+/// Formats `vis` followed by a space, inherited visibility is left out.
+pub fn format_visibility(vis: &Visibility, r: &mut Code) {
+    match vis {
+        Visibility::Public => {
+            r.push("pub ");
+        }
+        Visibility::Crate => {
+            r.push("pub(crate) ");
+        }
+        // `pub(self)` is the same as private
+        Visibility::Restricted { path, .. } if path == "self" => {}
+        Visibility::Restricted { path, .. } if path == "crate" || path == "super" => {
+            r.push(&format!("pub({}) ", path));
+        }
+        Visibility::Restricted { path, .. } => {
+            r.push(&format!("pub(in {}) ", path));
+        }
+        Visibility::Default => {}
     }
+}
+
+/// Formats the signature of `f` the way rustfmt lays it out in `width` columns: on one line when
+/// it fits and with one argument per line otherwise, followed by the where clause.
+pub fn format_fn(f: &data::Fn, with_parents: bool, width: usize, r: &mut Code) {
+    format_attrs(&f.item.attrs, r);
+    let flat = r.text_len(|r| format_fn_sig(f, with_parents, false, r));
+    let wrap = flat > width && !f.decl.inputs.is_empty();
+    format_fn_sig(f, with_parents, wrap, r);
+    if has_wheres(&f.generics) {
+        r.push("\n");
+        format_wheres(&f.generics.params, &f.generics.where_predicates, true, r);
+    }
+}
 
-    r.push(name).text_color(theme::FN_COLOR);
+fn format_fn_sig(f: &data::Fn, with_parents: bool, wrap: bool, r: &mut Code) {
+    format_visibility(&f.item.visibility, r);
+    format_qualifiers(&f.header, &f.abi, r);
+    r.push("fn ");
 
+    if with_parents {
+        for i in &f.item.parents {
+            r.push(&i).color(Syntax::Mod);
+            r.push("::");
+        }
+    }
+    r.push(&f.item.name).color(Syntax::Fn);
+
+    let gens = &f.generics;
     if !gens.params.iter().all(|x| x.name.starts_with("impl ")) {
         r.push("<");
         format_generics_def(
             gens.params.iter().filter(|x| !x.name.starts_with("impl ")),
-            false,
+            true,
             r,
         );
         r.push(">");
     }
 
     r.push("(");
-    let mut is_first = true;
-    for (name, ty) in &decl.inputs {
-        if !is_first {
+    for (i, (name, ty)) in f.decl.inputs.iter().enumerate() {
+        if wrap {
+            r.push("\n    ");
+        } else if i != 0 {
             r.push(", ");
+        }
+        format_arg(name, ty, i == 0, r);
+        if wrap {
+            r.push(",");
+        }
+    }
+    if f.decl.c_variadic {
+        if wrap {
+            r.push("\n    ...");
+        } else if f.decl.inputs.is_empty() {
+            r.push("...");
         } else {
-            is_first = false;
-            if name == "self" {
-                match ty {
-                    Type::BorrowedRef {
-                        lifetime,
-                        mutable,
-                        type_: _,
-                    } => {
-                        r.push("&");
-                        if let Some(lf) = lifetime {
-                            r.push(lf);
-                            r.push(" ");
-                        }
-                        if *mutable {
-                            r.push("mut ");
-                        }
-                        r.push("self");
-                        continue;
-                    }
-                    Type::Generic(s) if s == "Self" => {
-                        r.push("self");
-                        continue;
-                    }
-                    _ => {}
-                }
-            }
+            r.push(", ...");
         }
-
-        r.push(name);
-        r.push(": ");
-        format_ty(ty, false, r);
+    }
+    if wrap {
+        r.push("\n");
     }
     r.push(")");
-    if let Some(ty) = &decl.output {
+    if let Some(ty) = &f.decl.output {
         r.push(" -> ");
         format_ty(ty, false, r);
     }
 }
 
+/// Formats the qualifiers and ABI that go before `fn`, in the order Rust requires them.
+fn format_qualifiers(header: &Vector<Qualifiers>, abi: &str, r: &mut Code) {
+    for (q, keyword) in &[
+        (Qualifiers::Const, "const "),
+        (Qualifiers::Async, "async "),
+        (Qualifiers::Unsafe, "unsafe "),
+    ] {
+        if header.contains(q) {
+            r.push(keyword);
+        }
+    }
+    // the ABI comes quoted, e.g. `"C"`
+    if abi != "\"Rust\"" && !abi.is_empty() {
+        r.push("extern ");
+        r.push(abi);
+        r.push(" ");
+    }
+}
+
+/// Formats a function argument, using the `&self` shorthands for the receiver.
+fn format_arg(name: &str, ty: &Type, is_first: bool, r: &mut Code) {
+    if is_first && name == "self" {
+        match ty {
+            Type::BorrowedRef {
+                lifetime,
+                mutable,
+                type_: _,
+            } => {
+                r.push("&");
+                if let Some(lf) = lifetime {
+                    r.push(lf);
+                    r.push(" ");
+                }
+                if *mutable {
+                    r.push("mut ");
+                }
+                r.push("self");
+                return;
+            }
+            Type::Generic(s) if s == "Self" => {
+                r.push("self");
+                return;
+            }
+            _ => {}
+        }
+    }
+    r.push(name);
+    r.push(": ");
+    format_ty(ty, false, r);
+}
+
+/// Whether `g` has bounds that go into a where clause.
+pub fn has_wheres(g: &Generics) -> bool {
+    !g.where_predicates.is_empty()
+        || g.params.iter().any(|p| {
+            !p.name.starts_with("impl ")
+                && matches!(&p.kind, GenericParamDefKind::Type { bounds, .. } if !bounds.is_empty())
+        })
+}
+
 pub fn format_seperated<'a, T>(
     items: impl Iterator<Item = T>,
     sep: &'a str,
-    r: &'a mut RichTextBuilder,
-    mut func: impl FnMut(T, &mut RichTextBuilder),
+    r: &'a mut Code,
+    mut func: impl FnMut(T, &mut Code),
 ) {
     let mut is_first = true;
     items.for_each(move |item| {
@@ -371,3 +976,192 @@ pub fn format_seperated<'a, T>(
         func(item, r);
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(name: &str) -> data::Item {
+        data::Item {
+            name: name.into(),
+            parents: Vector::new(),
+            id: Id(name.into()),
+            visibility: Visibility::Public,
+            private: false,
+            attrs: Vector::new(),
+            deprecation: None,
+            cfg: None,
+            short_doc: None,
+            doc: None,
+            paths: Arc::default(),
+        }
+    }
+
+    /// The generics `<T>` with `T: Copy` in the where clause.
+    fn copy_generics() -> Generics {
+        let copy = GenericBound::TraitBound {
+            trait_: Type::ResolvedPath {
+                name: "Copy".into(),
+                id: Id("Copy".into()),
+                args: None,
+                param_names: Vector::new(),
+            },
+            generic_params: Vector::new(),
+            modifier: TraitBoundModifier::None,
+        };
+        Generics {
+            params: vec![GenericParamDef {
+                name: "T".into(),
+                kind: GenericParamDefKind::Type {
+                    bounds: vec![copy].into(),
+                    default: None,
+                },
+            }]
+            .into(),
+            where_predicates: Vector::new(),
+        }
+    }
+
+    fn struct_decl(struct_type: StructType, fields: &[&str]) -> String {
+        let fields = fields
+            .iter()
+            .map(|name| data::Field {
+                item: item(name),
+                ty: Type::Generic("T".into()),
+            })
+            .collect();
+        let s = data::Struct {
+            item: item("Foo"),
+            struct_type,
+            generics: copy_generics(),
+            fields,
+            fields_stripped: false,
+            impls: Vector::new(),
+            trait_impls: Vector::new(),
+            auto_impls: Vector::new(),
+        };
+        let mut r = Code::new();
+        format_struct_decl(&s, &mut r);
+        r.as_str().to_string()
+    }
+
+    fn func(name: &str, inputs: Vec<(&str, Type)>, generics: Generics) -> data::Fn {
+        data::Fn {
+            item: item(name),
+            decl: FnDecl {
+                inputs: inputs
+                    .into_iter()
+                    .map(|(name, ty)| (name.to_string(), ty))
+                    .collect(),
+                output: Some(Type::Primitive("bool".into())),
+                c_variadic: false,
+            },
+            generics,
+            header: Vector::new(),
+            abi: "\"Rust\"".into(),
+        }
+    }
+
+    fn signature(f: &data::Fn, width: usize) -> String {
+        let mut r = Code::new();
+        format_fn(f, false, width, &mut r);
+        r.as_str().to_string()
+    }
+
+    #[test]
+    fn fn_wraps_arguments_that_dont_fit() {
+        let self_ref = Type::BorrowedRef {
+            lifetime: None,
+            mutable: true,
+            type_: Arc::new(Type::Generic("Self".into())),
+        };
+        let f = func(
+            "resize",
+            vec![
+                ("self", self_ref),
+                ("len", Type::Primitive("usize".into())),
+                ("fill", Type::Primitive("u8".into())),
+            ],
+            Generics {
+                params: Vector::new(),
+                where_predicates: Vector::new(),
+            },
+        );
+        let flat = "pub fn resize(&mut self, len: usize, fill: u8) -> bool";
+        assert_eq!(signature(&f, flat.len()), flat);
+        assert_eq!(
+            signature(&f, flat.len() - 1),
+            "pub fn resize(\n    &mut self,\n    len: usize,\n    fill: u8,\n) -> bool"
+        );
+
+        // without arguments there is nothing to wrap
+        let empty = func("new", Vec::new(), f.generics.clone());
+        assert_eq!(signature(&empty, 1), "pub fn new() -> bool");
+    }
+
+    #[test]
+    fn fn_where_clause_follows_the_signature() {
+        let f = func(
+            "copy",
+            vec![("value", Type::Generic("T".into()))],
+            copy_generics(),
+        );
+        assert_eq!(
+            signature(&f, 100),
+            "pub fn copy<T>(value: T) -> bool\nwhere\n    T: Copy,"
+        );
+        // the where clause doesn't count towards the width of the signature
+        assert_eq!(
+            signature(&f, "pub fn copy<T>(value: T) -> bool".len()),
+            "pub fn copy<T>(value: T) -> bool\nwhere\n    T: Copy,"
+        );
+    }
+
+    #[test]
+    fn where_clause_before_semicolon_has_no_trailing_comma() {
+        assert_eq!(
+            struct_decl(StructType::Unit, &[]),
+            "pub struct Foo<T>\nwhere\n    T: Copy;"
+        );
+        assert_eq!(
+            struct_decl(StructType::Tuple, &["0"]),
+            "pub struct Foo<T>(pub T)\nwhere\n    T: Copy;"
+        );
+    }
+
+    #[test]
+    fn where_clause_before_body_has_trailing_comma() {
+        assert_eq!(
+            struct_decl(StructType::Plain, &["x"]),
+            "pub struct Foo<T>\nwhere\n    T: Copy,\n{\n    pub x: T,\n}"
+        );
+    }
+
+    #[test]
+    fn paths_are_short_unless_ambiguous_or_full() {
+        let field = data::Field {
+            item: item("inner"),
+            ty: Type::ResolvedPath {
+                name: "io::Error".into(),
+                id: Id("1".into()),
+                args: None,
+                param_names: Vector::new(),
+            },
+        };
+        let mut paths = PagePaths::default();
+        paths.full.insert(Id("1".into()), "std::io::Error".into());
+        let format = |paths: &PagePaths, full| {
+            let mut r = Code::with_paths(Arc::new(paths.clone()), full);
+            format_field(&field, &mut r);
+            r.as_str().to_string()
+        };
+
+        assert_eq!(format(&paths, false), "pub inner: Error");
+        assert_eq!(format(&paths, true), "pub inner: std::io::Error");
+        paths.ambiguous.insert(Id("1".into()));
+        assert_eq!(format(&paths, false), "pub inner: std::io::Error");
+        paths.ambiguous.clear();
+        paths.show_full = true;
+        assert_eq!(format(&paths, false), "pub inner: std::io::Error");
+    }
+}
@@ -0,0 +1,236 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::{fmt, thread};
+
+use druid::{ExtEventSink, Selector, SingleUse, Target};
+use rustdoc_types as rdoc;
+
+use crate::browse::Settings;
+use crate::data::Progress;
+use crate::delegate::Loaded;
+use crate::watch::watch;
+use crate::{cache, cargo};
+
+/// Submitted once a crate finished loading on a background thread, with the generation of the
+/// `Delegate::open` that started the load. Results of loads replaced by another are ignored.
+pub const LOADED: Selector<(usize, SingleUse<Result<Loaded, LoadError>>)> =
+    Selector::new("druid-rustdoc.loaded");
+/// How far a load got, with its generation like [`LOADED`].
+pub const PROGRESS: Selector<(usize, Progress)> = Selector::new("druid-rustdoc.load-progress");
+
+/// How often reading reports progress, in bytes.
+const CHUNK_SIZE: u64 = 4 << 20;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+#[derive(Debug, Clone, Copy)]
+enum Compression {
+    Gzip,
+    Zstd,
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    Parse(rdoc::Error),
+    Build(String),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io(e) => write!(f, "failed to read file: {}", e),
+            LoadError::Parse(e) => e.fmt(f),
+            LoadError::Build(e) => e.fmt(f),
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> Self {
+        LoadError::Io(e)
+    }
+}
+
+impl From<rdoc::Error> for LoadError {
+    fn from(e: rdoc::Error) -> Self {
+        LoadError::Parse(e)
+    }
+}
+
+/// Reads the crate at `path`, which may be gzip or zstd compressed.
+pub fn read_crate(path: &Path) -> Result<rdoc::Crate, LoadError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    if let Some(compression) = compression(&mut reader)? {
+        return parse_compressed(path, reader, compression);
+    }
+    let mut data = String::new();
+    reader.read_to_string(&mut data)?;
+    Ok(rdoc::parse(&data)?)
+}
+
+/// Loads the crate at `path` on the current thread for the commands that don't open a window,
+/// documenting cargo projects first with their output on stderr.
+pub fn load_headless(
+    path: &Path,
+    build: &cargo::Options,
+    cache: bool,
+) -> Result<rdoc::Crate, LoadError> {
+    let json = match cargo::manifest_path(path) {
+        Some(manifest) => cargo::document(&manifest, build, &mut |line| eprintln!("{}", line))?,
+        None => path.to_path_buf(),
+    };
+    if let Some(krate) = cache.then(|| cache::load(&json)).flatten() {
+        return Ok(krate);
+    }
+    let krate = read_crate(&json)?;
+    if cache {
+        cache::store(&json, &krate);
+    }
+    Ok(krate)
+}
+
+/// Loads the crate at `path` on a background thread, see [`load_blocking`].
+pub fn load(path: PathBuf, cache: bool, settings: Settings, generation: usize, sink: ExtEventSink) {
+    thread::spawn(move || load_blocking(path, cache, settings, generation, sink));
+}
+
+/// Loads the crate at `path` reporting [`PROGRESS`], builds its first page with `settings`,
+/// submits both as [`LOADED`] and then watches the file for changes. Everything is submitted with
+/// `generation`.
+///
+/// With `cache` the crate is read from the binary cache when it is up to date, and cached
+/// otherwise.
+pub fn load_blocking(
+    path: PathBuf,
+    cache: bool,
+    settings: Settings,
+    generation: usize,
+    sink: ExtEventSink,
+) {
+    let cached = if cache {
+        progress(&sink, generation, Progress::ReadingCache);
+        cache::load(&path)
+    } else {
+        None
+    };
+    let fresh = cached.is_none();
+    let result = match cached {
+        Some(krate) => Ok(krate),
+        None => read_with_progress(&path, generation, &sink),
+    };
+    let loaded = result.is_ok();
+    // cloning is cheap, the crate is made of persistent collections
+    let uncached = result.as_ref().ok().filter(|_| cache && fresh).cloned();
+    if loaded {
+        progress(&sink, generation, Progress::BuildingPage);
+    }
+    // the first page of a big crate takes a while to build, keep it off the UI thread
+    let result = result.map(|krate| Loaded::new(krate, &settings));
+    if sink
+        .submit_command(LOADED, (generation, SingleUse::new(result)), Target::Auto)
+        .is_err()
+    {
+        return;
+    }
+    if let Some(krate) = uncached {
+        cache::store(&path, &krate);
+    }
+    if loaded {
+        watch(path, generation, sink);
+    }
+}
+
+fn read_with_progress(
+    path: &Path,
+    generation: usize,
+    sink: &ExtEventSink,
+) -> Result<rdoc::Crate, LoadError> {
+    let file = File::open(path)?;
+    let total = file.metadata()?.len();
+    let mut reader = BufReader::new(ProgressReader {
+        inner: file,
+        read: 0,
+        reported: 0,
+        total,
+        generation,
+        sink,
+    });
+    if let Some(compression) = compression(&mut reader)? {
+        // the progress of reading the file is also the progress of parsing it here
+        return parse_compressed(path, reader, compression);
+    }
+
+    let mut data = String::with_capacity(total as usize);
+    reader.read_to_string(&mut data)?;
+    progress(sink, generation, Progress::Parsing);
+    Ok(rdoc::parse(&data)?)
+}
+
+/// Detects compressed files by their magic bytes.
+fn compression(reader: &mut impl BufRead) -> io::Result<Option<Compression>> {
+    let start = reader.fill_buf()?;
+    Ok(if start.starts_with(GZIP_MAGIC) {
+        Some(Compression::Gzip)
+    } else if start.starts_with(ZSTD_MAGIC) {
+        Some(Compression::Zstd)
+    } else {
+        None
+    })
+}
+
+/// Decompresses `reader`, the file at `path`, while streaming it into the parser. Formats that
+/// have to be upgraded decompress the file again.
+fn parse_compressed<'a>(
+    path: &Path,
+    reader: impl BufRead + 'a,
+    compression: Compression,
+) -> Result<rdoc::Crate, LoadError> {
+    let reader = decompress(reader, compression)?;
+    let reopen = || decompress(BufReader::new(File::open(path)?), compression);
+    Ok(rdoc::parse_reader(reader, reopen)?)
+}
+
+fn decompress<'a>(
+    reader: impl BufRead + 'a,
+    compression: Compression,
+) -> io::Result<BufReader<Box<dyn Read + 'a>>> {
+    let decoder: Box<dyn Read + 'a> = match compression {
+        Compression::Gzip => Box::new(flate2::bufread::MultiGzDecoder::new(reader)),
+        Compression::Zstd => Box::new(zstd::Decoder::with_buffer(reader)?),
+    };
+    Ok(BufReader::new(decoder))
+}
+
+/// Reports how much of the file was read as [`Progress::Reading`].
+struct ProgressReader<'a, R> {
+    inner: R,
+    read: u64,
+    reported: u64,
+    total: u64,
+    generation: usize,
+    sink: &'a ExtEventSink,
+}
+
+impl<R: Read> Read for ProgressReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read += n as u64;
+        if n == 0 || self.read - self.reported >= CHUNK_SIZE {
+            self.reported = self.read;
+            let (read, total) = (self.read, self.total);
+            progress(
+                self.sink,
+                self.generation,
+                Progress::Reading { read, total },
+            );
+        }
+        Ok(n)
+    }
+}
+
+fn progress(sink: &ExtEventSink, generation: usize, progress: Progress) {
+    let _ = sink.submit_command(PROGRESS, (generation, progress), Target::Auto);
+}
@@ -1,26 +1,100 @@
-use std::env;
+// `rustdoc_types::Crate` is deeply nested, proving it `Send` needs a higher limit.
+#![recursion_limit = "512"]
 
-use druid::{AppLauncher, Selector, WindowDesc};
+use std::path::PathBuf;
+use std::process;
+use std::sync::Arc;
+
+use druid::im::Vector;
+use druid::{AppLauncher, FileInfo, Selector, WindowDesc};
 use rustdoc_types::Id;
 use theme::configure_env;
+mod attrs;
+mod browse;
+mod cache;
+mod cargo;
+mod cfg;
+mod cli;
 mod data;
 mod delegate;
+mod export;
 mod format;
+mod load;
 mod md;
+mod recent;
+mod term;
 mod theme;
+mod tui;
 mod view;
+mod watch;
 mod widgets;
 
 const GOTO_ITEM: Selector<Id> = Selector::new("druid-rustdoc.goto-item");
+const GO_BACK: Selector = Selector::new("druid-rustdoc.go-back");
+/// Opens a JSON file or cargo project, e.g. from the recent crates.
+const OPEN_PATH: Selector<Arc<PathBuf>> = Selector::new("druid-rustdoc.open-path");
+const TOGGLE_PRIVATE: Selector = Selector::new("druid-rustdoc.toggle-private");
+const TOGGLE_FULL_PATHS: Selector = Selector::new("druid-rustdoc.toggle-full-paths");
+/// Lists only items with the next attribute of `attrs::SHOWN`.
+const CYCLE_ATTR_FILTER: Selector = Selector::new("druid-rustdoc.cycle-attr-filter");
+const TOGGLE_DEPRECATED: Selector = Selector::new("druid-rustdoc.toggle-deprecated");
+/// Shows the index of deprecated items.
+const SHOW_DEPRECATED: Selector = Selector::new("druid-rustdoc.show-deprecated");
+const TOGGLE_FEATURE_FILTER: Selector = Selector::new("druid-rustdoc.toggle-feature-filter");
+/// Enables or disables a crate feature in the feature filter.
+const TOGGLE_FEATURE: Selector<String> = Selector::new("druid-rustdoc.toggle-feature");
+/// Exports the current page and the pages below it as Markdown into the chosen directory.
+const EXPORT_MARKDOWN: Selector<FileInfo> = Selector::new("druid-rustdoc.export-markdown");
 
 fn main() {
-    let json_path = env::args().nth(1).unwrap();
-
-    let del = delegate::Delegate::new(json_path.as_ref());
-    let data = del.data();
+    let args = cli::parse();
+    if let Some(command) = &args.command {
+        let path = args.path.as_deref().expect("commands take a path");
+        let result = load::load_headless(path, &args.build, !args.no_cache)
+            .map_err(|e| e.to_string())
+            .and_then(|krate| match command {
+                cli::Command::Markdown { item, out } => {
+                    export::markdown_command(krate, item.as_deref(), out.as_deref())
+                }
+                cli::Command::Html { out } => export::html_command(krate, out),
+                cli::Command::Show { item } => term::show_command(krate, item),
+                cli::Command::Tui => tui::run(krate),
+            });
+        if let Err(e) = result {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
     let window = WindowDesc::new(view::ui_builder()).show_titlebar(false);
+    let launcher = AppLauncher::with_window(window);
+
+    let mut del = delegate::Delegate::new(
+        launcher.get_external_handle(),
+        !args.no_cache,
+        args.build,
+    );
+    let mut data = data::App {
+        page: data::Page::Welcome(data::Welcome {
+            recent: Vector::new(),
+        }),
+        notification: None,
+        includes_private: false,
+        show_private: true,
+        full_paths: false,
+        attr_filter: None,
+        hide_deprecated: false,
+        features: Vector::new(),
+        feature_filter: false,
+    };
+    data.page = match args.path {
+        Some(path) => del.open(path, browse::Settings::new(&data)),
+        None => data::Page::Welcome(data::Welcome {
+            recent: recent::load(),
+        }),
+    };
 
-    AppLauncher::with_window(window)
+    launcher
         .log_to_console()
         .delegate(del)
         .configure_env(|env, _| configure_env(env))
@@ -1,46 +1,92 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
 use druid::text::{AttributesAdder, RichTextBuilder};
 
 use druid::{
     text::{Attribute, AttributeSpans, RichText},
     Color, FontStyle, FontWeight,
 };
-use pulldown_cmark::{Event as ParseEvent, Parser, Tag};
+use pulldown_cmark::{Alignment, Event as ParseEvent, Options, Parser, Tag};
+use rustdoc_types::{Deprecation, Id};
 use syntect::easy::HighlightLines;
 use syntect::highlighting::ThemeSet;
 use syntect::parsing::SyntaxSet;
 use syntect::util::LinesWithEndings;
 
+use crate::data::Doc;
 use crate::delegate::OPEN_LINK;
 use crate::theme;
 const BLOCKQUOTE_COLOR: Color = Color::grey8(0x88);
 const LINK_COLOR: Color = Color::from_rgba32_u32(0x39AFD7FF);
 
+thread_local! {
+    // `RichText` isn't `Send`, the docs are only rendered on the UI thread anyway
+    static RENDERED: RefCell<HashMap<Id, RichText>> = RefCell::new(HashMap::new());
+}
+
+/// Renders the docs of an item, reusing the result of earlier calls.
+pub fn render_doc(doc: &Doc) -> RichText {
+    RENDERED.with(|cache| {
+        cache
+            .borrow_mut()
+            .entry(doc.id.clone())
+            .or_insert_with(|| markdown_to_text(&doc.markdown))
+            .clone()
+    })
+}
+
+/// Forgets all rendered docs, e.g. after the crate was reloaded.
+pub fn clear_rendered() {
+    RENDERED.with(|cache| cache.borrow_mut().clear());
+}
+
+/// The note on pages of deprecated items, in Markdown.
+pub fn deprecation_note(d: &Deprecation) -> String {
+    let mut text = match &d.since {
+        Some(since) => format!("**Deprecated since {}**", since),
+        None => "**Deprecated**".to_string(),
+    };
+    if let Some(note) = &d.note {
+        text.push_str(": ");
+        text.push_str(note);
+    }
+    text
+}
+
 // Parse a markdown string and generate a `RichText` object with
 /// the appropriate attributes.
 pub fn markdown_to_text(text: &str) -> RichText {
     let mut builder = RichTextBuilder::new();
     let mut tag_stack = Vec::new();
-    let mut is_code = false;
+    let mut blocks = Blocks::default();
+    let mut table: Option<Table> = None;
 
-    let parser = Parser::new(text);
+    let parser = Parser::new_ext(text, Options::ENABLE_TABLES | Options::ENABLE_TASKLISTS);
     for event in parser {
+        if let Some(t) = &mut table {
+            if let ParseEvent::End(tag @ Tag::Table(_)) = event {
+                push_table(&mut builder, &t.lines());
+                blocks.end(&tag);
+                table = None;
+            } else {
+                t.event(event);
+            }
+            continue;
+        }
         match event {
             ParseEvent::Start(tag) => {
-                if let Tag::CodeBlock(..) = tag {
-                    is_code = true;
-                } else if let Tag::Item = tag {
-                    builder.push("• ");
-                }
-                tag_stack.push((builder.len(), tag));
-            }
-            ParseEvent::Text(mut txt) => {
-                if is_code {
-                    txt = txt
-                        .split_inclusive('\n')
-                        .filter(|l| !l.starts_with("# "))
-                        .collect::<String>()
-                        .into();
+                builder.push(&blocks.start(&tag));
+                match tag {
+                    Tag::Table(alignments) => table = Some(Table::new(alignments)),
+                    tag => tag_stack.push((builder.len(), tag)),
                 }
+            }
+            ParseEvent::Text(txt) if blocks.is_code() => {
+                builder.push(&blocks.code(&txt));
+            }
+            ParseEvent::Text(txt) => {
+                builder.push(&blocks.flush());
                 builder.push(&txt);
                 builder.push(" ");
             }
@@ -49,29 +95,25 @@ pub fn markdown_to_text(text: &str) -> RichText {
                     .pop()
                     .expect("parser does not return unbalanced tags");
                 assert_eq!(end_tag, tag, "mismatched tags?");
-                match tag {
-                    Tag::CodeBlock(_) => {
-                        let (buffer, attrs) = builder.raw_parts();
-                        highlighting_code(buffer, start_off, attrs);
-                        is_code = false;
-                    }
-                    _ => {}
+                if let Tag::CodeBlock(_) = tag {
+                    let (buffer, attrs) = builder.raw_parts();
+                    highlighting_code(buffer, start_off, attrs);
                 }
                 add_attribute_for_tag(
                     &tag,
                     builder.add_attributes_for_range(start_off..builder.len()),
                 );
-                for _ in 0..newlines_after_tag(&tag) {
-                    builder.push("\n");
-                }
+                blocks.end(&tag);
             }
             ParseEvent::Code(txt) => {
+                builder.push(&blocks.flush());
                 builder
                     .push(&txt)
                     .font_descriptor(theme::CODE_FONT)
                     .text_color(theme::CODE_COLOR);
             }
             ParseEvent::Html(txt) => {
+                builder.push(&blocks.flush());
                 builder
                     .push(&txt)
                     .font_descriptor(theme::CODE_FONT)
@@ -83,27 +125,264 @@ pub fn markdown_to_text(text: &str) -> RichText {
 
             ParseEvent::FootnoteReference(_) => {}
             ParseEvent::SoftBreak => {}
-            ParseEvent::Rule => {}
-            ParseEvent::TaskListMarker(_) => {}
+            ParseEvent::Rule => {
+                builder.push(&blocks.rule()).text_color(BLOCKQUOTE_COLOR);
+            }
+            ParseEvent::TaskListMarker(checked) => {
+                builder.push(&blocks.task(checked));
+            }
         }
     }
-    let (string, _) = builder.raw_parts();
-    if string.ends_with("\n\n") {
-        string.truncate(string.len() - 2);
-    }
     builder.build()
 }
 
-fn newlines_after_tag(tag: &Tag) -> usize {
-    match tag {
-        Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link(..) => 0,
-        Tag::Item => 1,
-        Tag::List(..) => 1,
-        Tag::Paragraph => 2,
-        _ => 2,
+/// The layout of blocks in docs, shared by the frontends so they number and indent lists,
+/// hide lines of doctests and space paragraphs alike. Line breaks after a block are held back
+/// until more text follows, so the docs don't end with empty lines.
+#[derive(Debug, Default)]
+pub struct Blocks {
+    /// The lists the text is in, innermost last.
+    lists: Vec<List>,
+    is_code: bool,
+    /// Line breaks to add before the next text.
+    breaks: usize,
+    /// Whether any text was added, breaks before the first one are dropped.
+    started: bool,
+}
+
+#[derive(Debug)]
+struct List {
+    /// The number of the next item, `None` for bullet lists.
+    next: Option<u64>,
+    /// The column the text of the current item starts at.
+    column: usize,
+}
+
+impl Blocks {
+    /// The text to add when `tag` starts, like the marker of a list item.
+    pub fn start(&mut self, tag: &Tag) -> String {
+        match tag {
+            Tag::List(start) => {
+                // a list nested in a tight item follows its text
+                self.end_line(1);
+                self.lists.push(List {
+                    next: *start,
+                    column: 0,
+                });
+                String::new()
+            }
+            Tag::Item => {
+                let depth = self.lists.len();
+                let indent = match depth {
+                    0 | 1 => 0,
+                    _ => self.lists[depth - 2].column,
+                };
+                let marker = match self.lists.last_mut() {
+                    Some(List { next: Some(n), .. }) => {
+                        let marker = format!("{}. ", n);
+                        *n += 1;
+                        marker
+                    }
+                    _ => BULLETS[(depth.max(1) - 1) % BULLETS.len()].to_string(),
+                };
+                if let Some(list) = self.lists.last_mut() {
+                    list.column = indent + marker.chars().count();
+                }
+                self.flush() + &" ".repeat(indent) + &marker
+            }
+            // later paragraphs of an item line up with its first one
+            Tag::Paragraph | Tag::BlockQuote if self.breaks > 0 && !self.lists.is_empty() => {
+                self.flush() + &" ".repeat(self.column())
+            }
+            Tag::CodeBlock(_) => {
+                self.is_code = true;
+                self.flush()
+            }
+            _ => self.flush(),
+        }
+    }
+
+    /// Ends `tag`, the line breaks after it are added with the next text.
+    pub fn end(&mut self, tag: &Tag) {
+        match tag {
+            Tag::Paragraph | Tag::Heading(_) | Tag::BlockQuote | Tag::Table(_) => self.end_block(),
+            Tag::CodeBlock(_) => {
+                self.is_code = false;
+                self.end_block();
+            }
+            Tag::Item => self.end_line(1),
+            Tag::List(_) => {
+                self.lists.pop();
+                // nested lists go on with the items of the outer one
+                self.end_line(if self.lists.is_empty() { 2 } else { 1 });
+            }
+            _ => {}
+        }
+    }
+
+    /// The line breaks to add before inline text, which is then added as it is.
+    pub fn flush(&mut self) -> String {
+        let breaks = if self.started { self.breaks } else { 0 };
+        self.breaks = 0;
+        self.started = true;
+        "\n".repeat(breaks)
+    }
+
+    /// Whether the text is inside a code block, where it is added with [`Blocks::code`].
+    pub fn is_code(&self) -> bool {
+        self.is_code
+    }
+
+    /// The lines of code block text without the hidden lines of doctests, indented for the list
+    /// they are in.
+    pub fn code(&mut self, text: &str) -> String {
+        let indent = " ".repeat(self.column());
+        let mut out = String::new();
+        for line in text.split_inclusive('\n').filter(|l| !is_hidden(l)) {
+            out.push_str(&self.flush());
+            let line = line.strip_suffix('\n').unwrap_or(line);
+            if !line.is_empty() {
+                out.push_str(&indent);
+                out.push_str(line);
+            }
+            self.breaks = 1;
+        }
+        out
+    }
+
+    /// A horizontal rule.
+    pub fn rule(&mut self) -> String {
+        let rule = self.flush() + "───";
+        self.end_block();
+        rule
+    }
+
+    /// The marker of a task list item.
+    pub fn task(&mut self, checked: bool) -> String {
+        self.flush() + if checked { "☑ " } else { "☐ " }
+    }
+
+    /// The column the text of the current list item starts at.
+    fn column(&self) -> usize {
+        self.lists.last().map_or(0, |l| l.column)
+    }
+
+    /// Ends a paragraph-like block, with an empty line after it unless it is in a list.
+    fn end_block(&mut self) {
+        self.end_line(if self.lists.is_empty() { 2 } else { 1 });
+    }
+
+    fn end_line(&mut self, breaks: usize) {
+        self.breaks = self.breaks.max(breaks);
     }
 }
 
+/// The markers of bullet lists, by how deep they are nested.
+const BULLETS: [&str; 3] = ["• ", "◦ ", "▪ "];
+
+/// Whether `line` of a doctest is hidden in the docs, like `# use std::io;`.
+fn is_hidden(line: &str) -> bool {
+    let line = line.trim();
+    line == "#" || line.starts_with("# ")
+}
+
+/// A table collected from parse events, laid out as lines of aligned columns for a monospaced
+/// font.
+pub struct Table {
+    alignments: Vec<Alignment>,
+    rows: Vec<Vec<String>>,
+    /// How many of the rows are the head.
+    head: usize,
+}
+
+impl Table {
+    pub fn new(alignments: Vec<Alignment>) -> Self {
+        Table {
+            alignments,
+            rows: Vec::new(),
+            head: 0,
+        }
+    }
+
+    /// Adds an event from inside the table, cells keep only their text.
+    pub fn event(&mut self, event: ParseEvent) {
+        match event {
+            ParseEvent::Start(Tag::TableHead) | ParseEvent::Start(Tag::TableRow) => {
+                self.rows.push(Vec::new())
+            }
+            ParseEvent::Start(Tag::TableCell) => {
+                if let Some(row) = self.rows.last_mut() {
+                    row.push(String::new());
+                }
+            }
+            ParseEvent::End(Tag::TableHead) => self.head = self.rows.len(),
+            ParseEvent::Text(text) | ParseEvent::Code(text) | ParseEvent::Html(text) => {
+                if let Some(cell) = self.rows.last_mut().and_then(|row| row.last_mut()) {
+                    cell.push_str(&text);
+                }
+            }
+            ParseEvent::SoftBreak | ParseEvent::HardBreak => {
+                if let Some(cell) = self.rows.last_mut().and_then(|row| row.last_mut()) {
+                    cell.push(' ');
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The rows with their cells padded to the column widths and aligned, and a rule under
+    /// the head. The flag marks lines of the head.
+    pub fn lines(&self) -> Vec<(String, bool)> {
+        let columns = self.rows.iter().map(Vec::len).max().unwrap_or(0);
+        let mut widths = vec![1; columns];
+        for row in &self.rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.trim().chars().count());
+            }
+        }
+        let mut lines = Vec::new();
+        for (i, row) in self.rows.iter().enumerate() {
+            let cells: Vec<_> = widths
+                .iter()
+                .enumerate()
+                .map(|(c, width)| {
+                    let cell = row.get(c).map_or("", |cell| cell.trim());
+                    let pad = width - cell.chars().count();
+                    let left = match self.alignments.get(c) {
+                        Some(Alignment::Right) => pad,
+                        Some(Alignment::Center) => pad / 2,
+                        _ => 0,
+                    };
+                    format!("{}{}{}", " ".repeat(left), cell, " ".repeat(pad - left))
+                })
+                .collect();
+            lines.push((cells.join(" │ ").trim_end().to_string(), i < self.head));
+            if i + 1 == self.head {
+                let rule: Vec<_> = widths.iter().map(|w| "─".repeat(*w)).collect();
+                lines.push((rule.join("─┼─"), false));
+            }
+        }
+        lines
+    }
+}
+
+/// Adds the lines of a table in the code font, with the head in bold.
+fn push_table(builder: &mut RichTextBuilder, lines: &[(String, bool)]) {
+    let start = builder.len();
+    for (i, (line, head)) in lines.iter().enumerate() {
+        if i > 0 {
+            builder.push("\n");
+        }
+        let attrs = builder.push(line);
+        if *head {
+            attrs.weight(FontWeight::BOLD);
+        }
+    }
+    builder
+        .add_attributes_for_range(start..builder.len())
+        .font_descriptor(theme::CODE_FONT);
+}
+
 fn add_attribute_for_tag(tag: &Tag, mut attrs: AttributesAdder) {
     match tag {
         Tag::Heading(lvl) => {
@@ -164,3 +443,122 @@ fn highlighting_code(txt: &str, range: usize, attrs: &mut AttributeSpans) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `markdown` laid out by [`Blocks`], without styling.
+    fn layout(markdown: &str) -> String {
+        let mut blocks = Blocks::default();
+        let mut out = String::new();
+        for event in Parser::new_ext(markdown, Options::ENABLE_TASKLISTS) {
+            match event {
+                ParseEvent::Start(tag) => out += &blocks.start(&tag),
+                ParseEvent::End(tag) => blocks.end(&tag),
+                ParseEvent::Text(text) if blocks.is_code() => out += &blocks.code(&text),
+                ParseEvent::Text(text) | ParseEvent::Code(text) => {
+                    out += &blocks.flush();
+                    out += &text;
+                }
+                ParseEvent::SoftBreak => out.push(' '),
+                ParseEvent::Rule => out += &blocks.rule(),
+                ParseEvent::TaskListMarker(checked) => out += &blocks.task(checked),
+                _ => {}
+            }
+        }
+        out
+    }
+
+    fn table(markdown: &str) -> Vec<(String, bool)> {
+        let mut events = Parser::new_ext(markdown, Options::ENABLE_TABLES);
+        let mut table = match events.next() {
+            Some(ParseEvent::Start(Tag::Table(alignments))) => Table::new(alignments),
+            event => panic!("not a table: {:?}", event),
+        };
+        events.for_each(|event| table.event(event));
+        table.lines()
+    }
+
+    #[test]
+    fn paragraphs() {
+        assert_eq!(
+            layout("# Title\n\nOne\nline.\n\n---\n\nTwo."),
+            "Title\n\nOne line.\n\n───\n\nTwo."
+        );
+    }
+
+    #[test]
+    fn nested_lists() {
+        assert_eq!(
+            layout("- a\n- b\n  - c\n    - d\n- e\n\nAfter."),
+            "• a\n• b\n  ◦ c\n    ▪ d\n• e\n\nAfter."
+        );
+        // nested items line up with the text of their parent, bullets go by the depth
+        assert_eq!(
+            layout("9. nine\n10. ten\n    - inner"),
+            "9. nine\n10. ten\n    ◦ inner"
+        );
+    }
+
+    #[test]
+    fn ordered_lists_count_from_their_start() {
+        assert_eq!(
+            layout("3. three\n4. four\n\n   1. one\n   2. two"),
+            "3. three\n4. four\n   1. one\n   2. two"
+        );
+    }
+
+    #[test]
+    fn later_paragraphs_of_items_line_up() {
+        assert_eq!(
+            layout("1. first\n\n   more of it\n\n2. second"),
+            "1. first\n   more of it\n2. second"
+        );
+    }
+
+    #[test]
+    fn task_lists() {
+        assert_eq!(layout("- [x] done\n- [ ] todo"), "• ☑ done\n• ☐ todo");
+    }
+
+    #[test]
+    fn code_hides_doctest_lines() {
+        assert_eq!(
+            layout("Text\n\n```\n# use std::io;\nlet a = 1;\n#\n\nlet b = 2;\n```\n\nEnd"),
+            "Text\n\nlet a = 1;\n\nlet b = 2;\n\nEnd"
+        );
+        // and is indented in lists
+        assert_eq!(
+            layout("- item\n\n  ```\n  code\n  ```\n- next"),
+            "• item\n  code\n• next"
+        );
+    }
+
+    #[test]
+    fn table_columns_are_aligned() {
+        let lines = table(
+            "| Name | Count | Note |\n\
+             |:-----|------:|:----:|\n\
+             | a | 1 | x |\n\
+             | long name | 100 |\n",
+        );
+        let lines: Vec<_> = lines.iter().map(|(l, head)| (l.as_str(), *head)).collect();
+        assert_eq!(
+            lines,
+            [
+                ("Name      │ Count │ Note", true),
+                ("──────────┼───────┼─────", false),
+                ("a         │     1 │  x", false),
+                ("long name │   100 │", false),
+            ]
+        );
+    }
+
+    #[test]
+    fn table_cells_keep_only_their_text() {
+        let lines = table("| `code` and **bold** |\n|---|\n| [link](url) |\n");
+        assert_eq!(lines[0].0, "code and bold");
+        assert_eq!(lines[2].0, "link");
+    }
+}
@@ -0,0 +1,43 @@
+use std::fs;
+use std::path::PathBuf;
+
+use druid::im::Vector;
+
+use crate::data::Recent;
+
+const MAX_RECENT: usize = 10;
+
+fn file() -> Option<PathBuf> {
+    Some(
+        dirs::config_dir()?
+            .join("druid-rustdoc")
+            .join("recent.json"),
+    )
+}
+
+/// The recently opened crates, most recent first.
+pub fn load() -> Vector<Recent> {
+    file()
+        .and_then(|f| fs::read(f).ok())
+        .and_then(|data| serde_json::from_slice(&data).ok())
+        .unwrap_or_default()
+}
+
+/// Moves `recent` to the front of the list, returning the new list.
+pub fn add(recent: Recent) -> Vector<Recent> {
+    let mut list = load();
+    list.retain(|r| r.path != recent.path);
+    list.push_front(recent);
+    list.truncate(MAX_RECENT);
+    if let Err(e) = save(&list) {
+        log::warn!("failed to save recent crates: {}", e);
+    }
+    list
+}
+
+fn save(list: &Vector<Recent>) -> Result<(), Box<dyn std::error::Error>> {
+    let file = file().ok_or("no config directory")?;
+    fs::create_dir_all(file.parent().unwrap())?;
+    fs::write(file, serde_json::to_vec_pretty(list)?)?;
+    Ok(())
+}
@@ -0,0 +1,275 @@
+use std::env;
+use std::io::{self, IsTerminal};
+use std::ops::Range;
+
+use pulldown_cmark::{Event, Options, Parser, Tag};
+use rustdoc_types as rdoc;
+
+use crate::delegate::Docs;
+use crate::export::{self, Entry, Output};
+use crate::format::Code;
+use crate::{md, theme};
+
+/// The color of links in docs, like the app's.
+pub const LINK_RGBA: u32 = 0x39afd7ff;
+/// The color of notes like the features an item needs.
+pub const NOTE_RGBA: u32 = 0x888888ff;
+
+/// Runs the `show` command, printing the page of `item` to stdout. Colors are only used when
+/// stdout is a terminal and `NO_COLOR` isn't set.
+pub fn show_command(krate: rdoc::Crate, item: &str) -> Result<(), String> {
+    let mut docs = Docs::new(krate);
+    let id = docs
+        .find(item)
+        .ok_or_else(|| format!("no item `{}` with a page", item))?;
+    let screen = docs.page(&id);
+    let color = io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none();
+    let page = export::render(docs.krate(), &id, &screen, Terminal::new(color));
+    println!("{}", page.out.trim_end());
+    Ok(())
+}
+
+/// How a run of text is shown, for frontends drawing to a terminal.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Style {
+    /// The text color as `0xRRGGBBAA`, the terminal's default when `None`.
+    pub color: Option<u32>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+impl Style {
+    pub fn color(rgba: u32) -> Self {
+        Style {
+            color: Some(rgba),
+            ..Style::default()
+        }
+    }
+}
+
+/// Renders docs to runs of styled text with `\n` between lines, keeping the structure of
+/// blocks. Links aren't followed in a terminal, only their text is shown.
+pub fn markdown_runs(text: &str) -> Vec<(String, Style)> {
+    let mut runs = Vec::new();
+    let mut styles = vec![Style::default()];
+    let mut blocks = md::Blocks::default();
+    let mut table: Option<md::Table> = None;
+    for event in Parser::new_ext(text, Options::ENABLE_TABLES | Options::ENABLE_TASKLISTS) {
+        let style = *styles.last().unwrap();
+        if let Some(t) = &mut table {
+            if let Event::End(tag @ Tag::Table(_)) = event {
+                for (i, (line, head)) in t.lines().into_iter().enumerate() {
+                    if i > 0 {
+                        push_run(&mut runs, "\n", style);
+                    }
+                    push_run(
+                        &mut runs,
+                        &line,
+                        Style {
+                            bold: head,
+                            ..style
+                        },
+                    );
+                }
+                blocks.end(&tag);
+                table = None;
+            } else {
+                t.event(event);
+            }
+            continue;
+        }
+        match event {
+            Event::Start(tag) => {
+                push_run(&mut runs, &blocks.start(&tag), style);
+                let mut inner = style;
+                match tag {
+                    Tag::Table(alignments) => {
+                        table = Some(md::Table::new(alignments));
+                        continue;
+                    }
+                    Tag::Heading(_) | Tag::Strong => inner.bold = true,
+                    Tag::Emphasis | Tag::BlockQuote => inner.italic = true,
+                    Tag::Link(..) => {
+                        inner.underline = true;
+                        inner.color = Some(LINK_RGBA);
+                    }
+                    Tag::CodeBlock(_) => inner.color = Some(theme::CODE_RGBA),
+                    _ => {}
+                }
+                styles.push(inner);
+            }
+            Event::End(tag) => {
+                styles.pop();
+                blocks.end(&tag);
+            }
+            Event::Text(text) if blocks.is_code() => {
+                // code blocks are set off by an indent, there is no font to tell them apart
+                let code = blocks.code(&text);
+                let lines: Vec<_> = code
+                    .split('\n')
+                    .map(|line| match line {
+                        "" => String::new(),
+                        line => format!("    {}", line),
+                    })
+                    .collect();
+                push_run(&mut runs, &lines.join("\n"), style);
+            }
+            Event::Text(text) | Event::Html(text) => {
+                push_run(&mut runs, &blocks.flush(), style);
+                push_run(&mut runs, &text, style);
+            }
+            Event::Code(text) => {
+                push_run(&mut runs, &blocks.flush(), style);
+                push_run(
+                    &mut runs,
+                    &text,
+                    Style {
+                        color: Some(theme::CODE_RGBA),
+                        ..style
+                    },
+                );
+            }
+            Event::SoftBreak => push_run(&mut runs, " ", style),
+            Event::HardBreak => push_run(&mut runs, "\n", style),
+            Event::Rule => push_run(&mut runs, &blocks.rule(), style),
+            Event::TaskListMarker(checked) => push_run(&mut runs, &blocks.task(checked), style),
+            Event::FootnoteReference(_) => {}
+        }
+    }
+    while let Some((text, _)) = runs.last_mut() {
+        text.truncate(text.trim_end().len());
+        if !text.is_empty() {
+            break;
+        }
+        runs.pop();
+    }
+    runs
+}
+
+/// Adds `text` to `runs`, joining it with the last run if that has the same style.
+fn push_run(runs: &mut Vec<(String, Style)>, text: &str, style: Style) {
+    if text.is_empty() {
+        return;
+    }
+    match runs.last_mut() {
+        Some((last, last_style)) if *last_style == style => last.push_str(text),
+        _ => runs.push((text.to_string(), style)),
+    }
+}
+
+/// Writes pages as text with ANSI colors.
+struct Terminal {
+    out: String,
+    color: bool,
+}
+
+impl Terminal {
+    fn new(color: bool) -> Self {
+        Terminal {
+            out: String::new(),
+            color,
+        }
+    }
+
+    /// `text` wrapped in the escape sequences for `style`.
+    fn paint(&self, text: &str, style: Style) -> String {
+        let mut codes = Vec::new();
+        if style.bold {
+            codes.push("1".to_string());
+        }
+        if style.italic {
+            codes.push("3".to_string());
+        }
+        if style.underline {
+            codes.push("4".to_string());
+        }
+        if let Some(rgba) = style.color {
+            let [r, g, b, _] = rgba.to_be_bytes();
+            codes.push(format!("38;2;{};{};{}", r, g, b));
+        }
+        if !self.color || codes.is_empty() {
+            return text.to_string();
+        }
+        format!("\x1b[{}m{}\x1b[0m", codes.join(";"), text)
+    }
+
+    /// Renders docs, runs without a color of their own get the one of `base`.
+    fn markdown(&self, text: &str, base: Style) -> String {
+        markdown_runs(text)
+            .into_iter()
+            .map(|(text, style)| {
+                let style = Style {
+                    color: style.color.or(base.color),
+                    ..style
+                };
+                self.paint(&text, style)
+            })
+            .collect()
+    }
+}
+
+impl Output for Terminal {
+    fn ext(&self) -> &'static str {
+        "txt"
+    }
+
+    fn title(&mut self, kind: &str, name: &str) {
+        let title = format!("{} {}", kind, name);
+        let bold = Style {
+            bold: true,
+            ..Style::default()
+        };
+        self.out.push_str(&self.paint(title.trim(), bold));
+        self.out.push_str("\n\n");
+    }
+
+    fn heading(&mut self, text: &str) {
+        let bold = Style {
+            bold: true,
+            ..Style::default()
+        };
+        self.out.push_str(&self.paint(text, bold));
+        self.out.push_str("\n\n");
+    }
+
+    fn code(&mut self, code: &Code, _links: &[(Range<usize>, String)]) {
+        for (text, syntax) in code.runs() {
+            let style = syntax.map_or_else(Style::default, |s| Style::color(s.rgba()));
+            self.out.push_str(&self.paint(text, style));
+        }
+        self.out.push_str("\n\n");
+    }
+
+    fn note(&mut self, markdown: &str, class: &str) {
+        let rgba = match class {
+            "deprecated" => theme::WARNING_RGBA,
+            _ => NOTE_RGBA,
+        };
+        let note = self.markdown(markdown, Style::color(rgba));
+        self.out.push_str(&note);
+        self.out.push_str("\n\n");
+    }
+
+    fn docs(&mut self, markdown: &str) {
+        let docs = self.markdown(markdown, Style::default());
+        self.out.push_str(&docs);
+        self.out.push_str("\n\n");
+    }
+
+    fn list(&mut self, entries: &[Entry]) {
+        for entry in entries {
+            let mut line = format!("  {}", entry.name);
+            for tag in &entry.tags {
+                line.push_str(&format!(" [{}]", tag));
+            }
+            if let Some(summary) = entry.summary {
+                line.push_str("  ");
+                line.push_str(&self.markdown(summary, Style::default()));
+            }
+            self.out.push_str(&line);
+            self.out.push('\n');
+        }
+        self.out.push('\n');
+    }
+}
@@ -11,22 +11,122 @@ pub const CONST_COLOR: Key<Color> = Key::new("druid-rustdoc.const-color");
 pub const TYPE_COLOR: Key<Color> = Key::new("druid-rustdoc.type-color");
 pub const PRIMITIVE_COLOR: Key<Color> = Key::new("druid-rustdoc.primitive-color");
 pub const CODE_COLOR: Key<Color> = Key::new("druid-rustdoc.code-color");
+pub const ERROR_COLOR: Key<Color> = Key::new("druid-rustdoc.error-color");
+pub const WARNING_COLOR: Key<Color> = Key::new("druid-rustdoc.warning-color");
+pub const ATTR_COLOR: Key<Color> = Key::new("druid-rustdoc.attr-color");
 pub const ICONS_FONT: Key<FontDescriptor> = Key::new("druid-rustdoc.icons-font");
 pub const CODE_FONT: Key<FontDescriptor> = Key::new("druid-rustdoc.code-font");
 
+/// The window background as `0xRRGGBBAA`.
+pub const BACKGROUND_RGBA: u32 = 0x0f1419ff;
+/// The text color, druid's default label color.
+pub const TEXT_RGBA: u32 = 0xf0f0eaff;
+pub const WARNING_RGBA: u32 = 0xe6b450ff;
+pub const CODE_RGBA: u32 = 0xffb454ff;
+
+/// What a span of formatted code is, which picks its color. Exports color code without an `Env`,
+/// so the default colors are kept here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Syntax {
+    Mod,
+    Struct,
+    Enum,
+    Fn,
+    Trait,
+    Const,
+    Type,
+    Primitive,
+    Attr,
+}
+
+impl Syntax {
+    pub const ALL: &'static [Syntax] = &[
+        Syntax::Mod,
+        Syntax::Struct,
+        Syntax::Enum,
+        Syntax::Fn,
+        Syntax::Trait,
+        Syntax::Const,
+        Syntax::Type,
+        Syntax::Primitive,
+        Syntax::Attr,
+    ];
+
+    pub fn key(self) -> Key<Color> {
+        match self {
+            Syntax::Mod => MOD_COLOR,
+            Syntax::Struct => STRUCT_COLOR,
+            Syntax::Enum => ENUM_COLOR,
+            Syntax::Fn => FN_COLOR,
+            Syntax::Trait => TRAIT_COLOR,
+            Syntax::Const => CONST_COLOR,
+            Syntax::Type => TYPE_COLOR,
+            Syntax::Primitive => PRIMITIVE_COLOR,
+            Syntax::Attr => ATTR_COLOR,
+        }
+    }
+
+    /// The default color as `0xRRGGBBAA`.
+    pub fn rgba(self) -> u32 {
+        match self {
+            Syntax::Mod => 0xacccf9ff,
+            Syntax::Struct => 0xffa0a5ff,
+            Syntax::Enum => 0x99e0c9ff,
+            Syntax::Fn => 0xfdd687ff,
+            Syntax::Trait => 0x39afd7ff,
+            Syntax::Const => 0x6380a0ff,
+            Syntax::Type => 0xcfbcf5ff,
+            Syntax::Primitive => 0x32889bff,
+            Syntax::Attr => 0x808080ff,
+        }
+    }
+
+    /// A short lowercase name, used as the CSS class in exported HTML.
+    pub fn name(self) -> &'static str {
+        match self {
+            Syntax::Mod => "mod",
+            Syntax::Struct => "struct",
+            Syntax::Enum => "enum",
+            Syntax::Fn => "fn",
+            Syntax::Trait => "trait",
+            Syntax::Const => "const",
+            Syntax::Type => "type",
+            Syntax::Primitive => "primitive",
+            Syntax::Attr => "attr",
+        }
+    }
+}
+
+/// Fades the text colors in `env`, used for items that aren't public.
+pub fn dim(env: &mut Env) {
+    for key in &[
+        LABEL_COLOR,
+        MOD_COLOR,
+        STRUCT_COLOR,
+        ENUM_COLOR,
+        FN_COLOR,
+        KEYWORD_COLOR,
+        TRAIT_COLOR,
+        CONST_COLOR,
+        TYPE_COLOR,
+        PRIMITIVE_COLOR,
+        CODE_COLOR,
+    ] {
+        let color = env.get(key);
+        env.set(key.clone(), color.with_alpha(0.5));
+    }
+}
+
 pub fn configure_env(env: &mut Env) {
-    env.set(BACKGROUND_DARK, Color::Rgba32(0x0f1419ff));
-    env.set(WINDOW_BACKGROUND_COLOR, Color::Rgba32(0x0f1419ff));
-    env.set(MOD_COLOR, Color::Rgba32(0xacccf9ff));
-    env.set(STRUCT_COLOR, Color::Rgba32(0xffa0a5ff));
-    env.set(ENUM_COLOR, Color::Rgba32(0x99e0c9ff));
-    env.set(FN_COLOR, Color::Rgba32(0xfdd687ff));
-    env.set(CONST_COLOR, Color::Rgba32(0x6380a0ff));
+    env.set(BACKGROUND_DARK, Color::Rgba32(BACKGROUND_RGBA));
+    env.set(WINDOW_BACKGROUND_COLOR, Color::Rgba32(BACKGROUND_RGBA));
+    for syntax in Syntax::ALL {
+        env.set(syntax.key(), Color::Rgba32(syntax.rgba()));
+    }
     env.set(KEYWORD_COLOR, Color::Rgba32(0xff7733ff));
-    env.set(TYPE_COLOR, Color::Rgba32(0xcfbcf5ff));
-    env.set(TRAIT_COLOR, Color::Rgba32(0x39AFD7ff));
-    env.set(CODE_COLOR, Color::Rgba32(0xffb454ff));
-    env.set(PRIMITIVE_COLOR, Color::Rgba32(0x32889bff));
+    env.set(CODE_COLOR, Color::Rgba32(CODE_RGBA));
+    env.set(ERROR_COLOR, Color::Rgba32(0xff3333ff));
+    env.set(WARNING_COLOR, Color::Rgba32(WARNING_RGBA));
     env.set(
         ICONS_FONT,
         FontDescriptor::new(FontFamily::new_unchecked("Segoe MDL2 Assets")),
@@ -0,0 +1,586 @@
+use std::io;
+use std::ops::Range;
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use rdoc::ItemKind;
+use rustdoc_types as rdoc;
+
+use crate::browse::{Action, Settings};
+use crate::delegate::{deprecated_id, opens, Docs};
+use crate::export::{self, Entry, Output};
+use crate::format::Code;
+use crate::{term, theme};
+
+const HELP: &str =
+    "↑↓ links  ⏎ open  ⌫ back  / search  d deprecated  p private  f paths  a attrs  q quit";
+const SEARCH_HELP: &str = "↑↓ select  ⏎ open  esc cancel";
+
+/// Runs the `tui` command, browsing the crate in the terminal until it is quit.
+pub fn run(krate: rdoc::Crate) -> Result<(), String> {
+    let mut tui = Tui::new(Docs::new(krate));
+    let mut terminal = ratatui::init();
+    let result = tui.run(&mut terminal);
+    ratatui::restore();
+    result.map_err(|e| format!("terminal error: {}", e))
+}
+
+/// The browser state, the counterpart of the window and its delegate.
+struct Tui {
+    docs: Docs,
+    settings: Settings,
+    page: Page,
+    /// `page` wrapped to the width it was last drawn at.
+    wrapped: Option<Wrapped>,
+    /// The index of the selected link.
+    selected: Option<usize>,
+    /// The first line shown.
+    scroll: usize,
+    /// Whether the next draw scrolls the selected link into view.
+    reveal: bool,
+    /// The number of lines shown when last drawn.
+    height: usize,
+    search: Option<Search>,
+    /// A message shown in the status line until the next key.
+    status: Option<String>,
+}
+
+struct Search {
+    query: String,
+    results: Vec<rdoc::Id>,
+    state: ListState,
+}
+
+impl Tui {
+    fn new(mut docs: Docs) -> Self {
+        let settings = Settings::default();
+        docs.set_settings(&settings);
+        let mut tui = Tui {
+            docs,
+            settings,
+            page: Page {
+                lines: Vec::new(),
+                links: Vec::new(),
+            },
+            wrapped: None,
+            selected: None,
+            scroll: 0,
+            reveal: false,
+            height: 0,
+            search: None,
+            status: None,
+        };
+        tui.show();
+        tui
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && !self.key(key) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Shows the current item of `docs` from the top.
+    fn show(&mut self) {
+        self.page = Page::new(&mut self.docs);
+        self.wrapped = None;
+        self.selected = if self.page.links.is_empty() {
+            None
+        } else {
+            Some(0)
+        };
+        self.scroll = 0;
+    }
+
+    /// Does `action` like the window does, showing the page it leads to.
+    fn act(&mut self, action: Action) {
+        if self.docs.apply(action, &mut self.settings) {
+            self.show();
+        } else {
+            // only going back can leave the page as it is
+            self.status = Some("No earlier item".into());
+        }
+    }
+
+    /// Handles `key`, returning whether to go on.
+    fn key(&mut self, key: KeyEvent) -> bool {
+        self.status = None;
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            return false;
+        }
+        if self.search.is_some() {
+            self.search_key(key);
+            return true;
+        }
+        let page = self.height.max(1) as isize;
+        match key.code {
+            KeyCode::Char('q') => return false,
+            KeyCode::Down | KeyCode::Char('j') | KeyCode::Tab => self.move_selection(1),
+            KeyCode::Up | KeyCode::Char('k') | KeyCode::BackTab => self.move_selection(-1),
+            KeyCode::PageDown | KeyCode::Char(' ') => self.scroll_by(page),
+            KeyCode::PageUp => self.scroll_by(-page),
+            KeyCode::Home | KeyCode::Char('g') => self.scroll_by(isize::MIN / 2),
+            KeyCode::End | KeyCode::Char('G') => self.scroll_by(isize::MAX / 2),
+            KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => {
+                if let Some(id) = self.selected.map(|i| self.page.links[i].clone()) {
+                    self.act(Action::Goto(id));
+                }
+            }
+            KeyCode::Backspace | KeyCode::Left | KeyCode::Char('h') => self.act(Action::Back),
+            KeyCode::Char('/') => {
+                self.search = Some(Search {
+                    query: String::new(),
+                    results: Vec::new(),
+                    state: ListState::default(),
+                })
+            }
+            KeyCode::Char('d') => self.act(Action::ShowDeprecated),
+            KeyCode::Char('p') => {
+                self.act(Action::TogglePrivate);
+                self.status = Some(if self.settings.show_private {
+                    "Showing private items".into()
+                } else {
+                    "Hiding private items".into()
+                });
+            }
+            KeyCode::Char('f') => {
+                self.act(Action::ToggleFullPaths);
+                self.status = Some(if self.settings.full_paths {
+                    "Showing full paths".into()
+                } else {
+                    "Showing short paths".into()
+                });
+            }
+            KeyCode::Char('a') => {
+                self.act(Action::CycleAttrFilter);
+                self.status = Some(match &self.settings.attr_filter {
+                    Some(attr) => format!("Listing items with #[{}]", attr),
+                    None => "Listing all items".into(),
+                });
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn search_key(&mut self, key: KeyEvent) {
+        let search = self.search.as_mut().unwrap();
+        let selected = search.state.selected();
+        match key.code {
+            KeyCode::Esc => self.search = None,
+            KeyCode::Enter => {
+                if let Some(id) = selected.and_then(|i| search.results.get(i)).cloned() {
+                    self.search = None;
+                    self.act(Action::Goto(id));
+                }
+            }
+            KeyCode::Down | KeyCode::Tab => {
+                let last = search.results.len().saturating_sub(1);
+                search.state.select(selected.map(|i| (i + 1).min(last)));
+            }
+            KeyCode::Up | KeyCode::BackTab => {
+                search.state.select(selected.map(|i| i.saturating_sub(1)));
+            }
+            KeyCode::Backspace => {
+                search.query.pop();
+                search.update(&self.docs);
+            }
+            KeyCode::Char(c) => {
+                search.query.push(c);
+                search.update(&self.docs);
+            }
+            _ => {}
+        }
+    }
+
+    /// Selects the link `delta` links away, or scrolls if the page has none.
+    fn move_selection(&mut self, delta: isize) {
+        match self.selected {
+            Some(selected) => {
+                let last = self.page.links.len() as isize - 1;
+                self.selected = Some((selected as isize + delta).max(0).min(last) as usize);
+                self.reveal = true;
+            }
+            None => self.scroll_by(delta),
+        }
+    }
+
+    /// Scrolls by `delta` lines, selecting the first link shown if the selected one scrolled
+    /// out of view.
+    fn scroll_by(&mut self, delta: isize) {
+        let wrapped = match &self.wrapped {
+            Some(wrapped) => wrapped,
+            None => return,
+        };
+        let max = wrapped.lines.len().saturating_sub(self.height) as isize;
+        self.scroll = (self.scroll as isize + delta).max(0).min(max) as usize;
+        let shown = self.scroll..self.scroll + self.height;
+        let selected_shown = self
+            .selected
+            .map_or(false, |i| shown.contains(&wrapped.link_lines[i]));
+        if !selected_shown {
+            if let Some(i) = wrapped.link_lines.iter().position(|l| shown.contains(l)) {
+                self.selected = Some(i);
+            }
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let background = Style::default()
+            .bg(color(theme::BACKGROUND_RGBA))
+            .fg(color(theme::TEXT_RGBA));
+        frame.render_widget(Block::new().style(background), frame.area());
+        let [main, status] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        match &mut self.search {
+            Some(search) => draw_search(frame, main, search, &self.docs),
+            None => self.draw_page(frame, main),
+        }
+
+        let (text, help) = match (&self.status, &self.search) {
+            (Some(status), _) => (status.clone(), HELP),
+            (None, Some(_)) => ("Search".to_string(), SEARCH_HELP),
+            (None, None) => (current_name(&self.docs), HELP),
+        };
+        let help_width = help.chars().count() as u16 + 1;
+        let [left, right] =
+            Layout::horizontal([Constraint::Min(0), Constraint::Length(help_width)]).areas(status);
+        let bar = Style::default().add_modifier(Modifier::REVERSED);
+        frame.render_widget(Paragraph::new(format!(" {}", text)).style(bar), left);
+        frame.render_widget(Paragraph::new(help).style(bar), right);
+    }
+
+    fn draw_page(&mut self, frame: &mut Frame, area: Rect) {
+        if self
+            .wrapped
+            .as_ref()
+            .map_or(true, |w| w.width != area.width)
+        {
+            self.wrapped = Some(Wrapped::new(&self.page, area.width));
+        }
+        let wrapped = self.wrapped.as_ref().unwrap();
+        self.height = area.height as usize;
+        if self.reveal {
+            if let Some(line) = self.selected.map(|i| wrapped.link_lines[i]) {
+                if line < self.scroll {
+                    self.scroll = line;
+                } else if line >= self.scroll + self.height {
+                    self.scroll = line + 1 - self.height;
+                }
+            }
+            self.reveal = false;
+        }
+        self.scroll = self
+            .scroll
+            .min(wrapped.lines.len().saturating_sub(self.height));
+
+        let selected = self.selected;
+        let lines: Vec<_> = wrapped
+            .lines
+            .iter()
+            .skip(self.scroll)
+            .take(self.height)
+            .map(|line| {
+                let spans: Vec<_> = line
+                    .iter()
+                    .map(|s| {
+                        let mut style = s.style;
+                        if s.link.is_some() && s.link == selected {
+                            style = style.add_modifier(Modifier::REVERSED);
+                        }
+                        Span::styled(s.text.clone(), style)
+                    })
+                    .collect();
+                Line::from(spans)
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(lines), area);
+    }
+}
+
+impl Search {
+    fn update(&mut self, docs: &Docs) {
+        self.results = docs.search(&self.query);
+        let selected = if self.results.is_empty() {
+            None
+        } else {
+            Some(0)
+        };
+        self.state.select(selected);
+    }
+}
+
+fn draw_search(frame: &mut Frame, area: Rect, search: &mut Search, docs: &Docs) {
+    let [input, results] =
+        Layout::vertical([Constraint::Length(2), Constraint::Min(0)]).areas(area);
+    frame.render_widget(Paragraph::new(format!("/{}", search.query)), input);
+    frame.set_cursor_position((input.x + 1 + search.query.chars().count() as u16, input.y));
+    let items: Vec<_> = search
+        .results
+        .iter()
+        .map(|id| {
+            let s = &docs.krate().paths[id];
+            let kind = match s.kind {
+                ItemKind::Module => "mod",
+                ItemKind::Struct => "struct",
+                ItemKind::Enum => "enum",
+                _ => "fn",
+            };
+            let path: Vec<_> = s.path.iter().cloned().collect();
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{:<7}", kind),
+                    tui_style(term::Style::color(term::NOTE_RGBA)),
+                ),
+                Span::raw(path.join("::")),
+            ]))
+        })
+        .collect();
+    let list = List::new(items).highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(list, results, &mut search.state);
+}
+
+/// The path of the current item, for the status line.
+fn current_name(docs: &Docs) -> String {
+    if *docs.current() == deprecated_id() {
+        return "Deprecated items".into();
+    }
+    match docs.krate().paths.get(docs.current()) {
+        Some(s) => s.path.iter().cloned().collect::<Vec<_>>().join("::"),
+        None => String::new(),
+    }
+}
+
+/// A run of text on a page.
+struct Segment {
+    text: String,
+    style: Style,
+    /// Which of the page's links this is.
+    link: Option<usize>,
+}
+
+/// A page as lines of segments, before wrapping.
+struct Page {
+    lines: Vec<Vec<Segment>>,
+    /// The items linked from the page that can be opened.
+    links: Vec<rdoc::Id>,
+}
+
+impl Page {
+    /// The page of the current item of `docs`.
+    fn new(docs: &mut Docs) -> Self {
+        let id = docs.current().clone();
+        let screen = docs.page(&id);
+        let view = View {
+            krate: docs.krate(),
+            page: Page {
+                lines: vec![Vec::new()],
+                links: Vec::new(),
+            },
+        };
+        export::render(docs.krate(), &id, &screen, view).page
+    }
+}
+
+/// `Page::lines` wrapped to a width.
+struct Wrapped {
+    width: u16,
+    lines: Vec<Vec<Segment>>,
+    /// The line each link starts on.
+    link_lines: Vec<usize>,
+}
+
+impl Wrapped {
+    fn new(page: &Page, width: u16) -> Self {
+        let width = width.max(1) as usize;
+        let mut lines = Vec::new();
+        let mut link_lines = vec![0; page.links.len()];
+        let mut placed = vec![false; page.links.len()];
+        for line in &page.lines {
+            let mut current: Vec<Segment> = Vec::new();
+            let mut column = 0;
+            for s in line {
+                for word in s.text.split_inclusive(' ') {
+                    if column > 0 && column + word.trim_end().chars().count() > width {
+                        lines.push(std::mem::take(&mut current));
+                        column = 0;
+                    }
+                    if let Some(link) = s.link {
+                        if !placed[link] {
+                            placed[link] = true;
+                            link_lines[link] = lines.len();
+                        }
+                    }
+                    column += word.chars().count();
+                    match current.last_mut() {
+                        Some(last) if last.style == s.style && last.link == s.link => {
+                            last.text.push_str(word)
+                        }
+                        _ => current.push(Segment {
+                            text: word.to_string(),
+                            style: s.style,
+                            link: s.link,
+                        }),
+                    }
+                }
+            }
+            lines.push(current);
+        }
+        Wrapped {
+            width: width as u16,
+            lines,
+            link_lines,
+        }
+    }
+}
+
+/// Lays a page out as segments.
+struct View<'a> {
+    krate: &'a rdoc::Crate,
+    page: Page,
+}
+
+impl View<'_> {
+    /// Adds `text` to the last line, `\n` starts a new one.
+    fn push(&mut self, text: &str, style: Style, link: Option<usize>) {
+        for (i, part) in text.split('\n').enumerate() {
+            if i > 0 {
+                self.page.lines.push(Vec::new());
+            }
+            if !part.is_empty() {
+                self.page.lines.last_mut().unwrap().push(Segment {
+                    text: part.to_string(),
+                    style,
+                    link,
+                });
+            }
+        }
+    }
+
+    fn push_markdown(&mut self, markdown: &str, base: term::Style) {
+        for (text, style) in term::markdown_runs(markdown) {
+            let style = term::Style {
+                color: style.color.or(base.color),
+                ..style
+            };
+            self.push(&text, tui_style(style), None);
+        }
+    }
+
+    /// Ends a block with an empty line.
+    fn end_block(&mut self) {
+        self.push("\n\n", Style::default(), None);
+    }
+
+    /// Adds `id` to the links of the page if it can be opened.
+    fn link(&mut self, id: &rdoc::Id) -> Option<usize> {
+        if !opens(self.krate, id) {
+            return None;
+        }
+        self.page.links.push(id.clone());
+        Some(self.page.links.len() - 1)
+    }
+}
+
+impl Output for View<'_> {
+    fn ext(&self) -> &'static str {
+        "txt"
+    }
+
+    fn title(&mut self, kind: &str, name: &str) {
+        let title = format!("{} {}", kind, name);
+        self.push(
+            title.trim(),
+            Style::default().add_modifier(Modifier::BOLD),
+            None,
+        );
+        self.end_block();
+    }
+
+    fn heading(&mut self, text: &str) {
+        let style = Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+        self.push(text, style, None);
+        self.end_block();
+    }
+
+    fn code(&mut self, code: &Code, _links: &[(Range<usize>, String)]) {
+        // links are followed by id, the paths of exported pages aren't needed
+        let mut offset = 0;
+        for (text, syntax) in code.runs() {
+            let range = offset..offset + text.len();
+            let link = code
+                .links()
+                .iter()
+                .find(|(r, _)| *r == range)
+                .and_then(|(_, id)| self.link(id));
+            let style = syntax.map_or_else(term::Style::default, |s| term::Style::color(s.rgba()));
+            self.push(text, tui_style(style), link);
+            offset = range.end;
+        }
+        self.end_block();
+    }
+
+    fn note(&mut self, markdown: &str, class: &str) {
+        let rgba = match class {
+            "deprecated" => theme::WARNING_RGBA,
+            _ => term::NOTE_RGBA,
+        };
+        self.push_markdown(markdown, term::Style::color(rgba));
+        self.end_block();
+    }
+
+    fn docs(&mut self, markdown: &str) {
+        self.push_markdown(markdown, term::Style::default());
+        self.end_block();
+    }
+
+    fn list(&mut self, entries: &[Entry]) {
+        for entry in entries {
+            self.push("  ", Style::default(), None);
+            let link = self.link(&entry.id);
+            let name = tui_style(term::Style::color(term::LINK_RGBA));
+            self.push(&entry.name, name, link);
+            for tag in &entry.tags {
+                let tag = format!(" [{}]", tag);
+                self.push(&tag, tui_style(term::Style::color(term::NOTE_RGBA)), None);
+            }
+            if let Some(summary) = entry.summary {
+                self.push("  ", Style::default(), None);
+                self.push_markdown(summary, term::Style::default());
+            }
+            self.push("\n", Style::default(), None);
+        }
+        self.push("\n", Style::default(), None);
+    }
+}
+
+fn color(rgba: u32) -> Color {
+    let [r, g, b, _] = rgba.to_be_bytes();
+    Color::Rgb(r, g, b)
+}
+
+fn tui_style(style: term::Style) -> Style {
+    let mut out = Style::default();
+    if let Some(rgba) = style.color {
+        out = out.fg(color(rgba));
+    }
+    if style.bold {
+        out = out.add_modifier(Modifier::BOLD);
+    }
+    if style.italic {
+        out = out.add_modifier(Modifier::ITALIC);
+    }
+    if style.underline {
+        out = out.add_modifier(Modifier::UNDERLINED);
+    }
+    out
+}
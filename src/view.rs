@@ -1,21 +1,315 @@
+use std::sync::Arc;
+use std::time::Duration;
+
 use druid::im::Vector;
 use druid::text::RichTextBuilder;
-use druid::widget::{CrossAxisAlignment, Flex, Label, List, RawLabel};
-use druid::{lens, Color, Data, Key, Widget, WidgetExt};
+use druid::widget::{
+    Button, Controller, CrossAxisAlignment, Flex, Label, LineBreaking, List, Maybe, ProgressBar,
+    RawLabel,
+};
+use druid::{
+    commands, lens, Color, Data, Env, Event, EventCtx, Insets, Key, TimerToken, UpdateCtx, Widget,
+    WidgetExt,
+};
 use druid_simple_table::Table;
-use rustdoc_types::GenericParamDefKind;
+use rustdoc_types::Deprecation;
 
+use crate::cfg::Cfg;
 use crate::data;
-use crate::format::{format_fn, format_generics_def, format_seperated, format_ty, format_wheres};
+use crate::delegate::{export_dialog, open_dialog};
+use crate::format::{
+    format_const, format_enum_decl, format_field, format_fn, format_generics_def, format_impl_head,
+    format_struct_decl, format_type_def, format_variant, Code,
+};
+use crate::theme::Syntax;
 use crate::widgets::*;
-use crate::{theme, GOTO_ITEM};
+use crate::{
+    attrs, md, theme, CYCLE_ATTR_FILTER, GOTO_ITEM, OPEN_PATH, SHOW_DEPRECATED, TOGGLE_DEPRECATED,
+    TOGGLE_FEATURE, TOGGLE_FEATURE_FILTER, TOGGLE_FULL_PATHS, TOGGLE_PRIVATE,
+};
+
+const NOTIFICATION_TIMEOUT: Duration = Duration::from_secs(3);
+
+pub fn ui_builder() -> impl Widget<data::App> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Fill)
+        .with_child(toolbar())
+        .with_child(feature_bar())
+        .with_flex_child(page(), 1.)
+        .with_child(notification())
+}
 
-pub fn ui_builder() -> impl Widget<data::Screen> {
-    data::Screen::matcher()
+fn page() -> impl Widget<data::App> {
+    let screen = data::Screen::matcher()
         .mod_(mod_())
         .struct_(struct_())
         .enum_(enum_())
         .fn_(fn_())
+        .index(index());
+    data::Page::matcher()
+        .welcome(welcome())
+        .loading(loading())
+        .docs(screen)
+        .lens(lens!(data::App, page))
+}
+
+fn toolbar() -> impl Widget<data::App> {
+    let private = Label::new(|show: &bool, _: &Env| {
+        if *show {
+            "Hide private items".to_string()
+        } else {
+            "Show private items".to_string()
+        }
+    })
+    .with_text_color(theme::PLACEHOLDER_COLOR)
+    .on_click(|ctx, _, _| ctx.submit_command(TOGGLE_PRIVATE))
+    .lens(lens!(data::App, show_private))
+    .empty_if(|app: &data::App, _| !app.includes_private);
+
+    let paths = Label::new(|full: &bool, _: &Env| {
+        if *full {
+            "Short paths".to_string()
+        } else {
+            "Full paths".to_string()
+        }
+    })
+    .with_text_color(theme::PLACEHOLDER_COLOR)
+    .on_click(|ctx, _, _| ctx.submit_command(TOGGLE_FULL_PATHS))
+    .lens(lens!(data::App, full_paths));
+
+    let attr_filter = Label::new(|filter: &Option<String>, _: &Env| match filter {
+        Some(attr) => format!("Only #[{}]", attr),
+        None => "All attributes".to_string(),
+    })
+    .with_text_color(theme::PLACEHOLDER_COLOR)
+    .on_click(|ctx, _, _| ctx.submit_command(CYCLE_ATTR_FILTER))
+    .lens(lens!(data::App, attr_filter));
+
+    let hide_deprecated = Label::new(|hide: &bool, _: &Env| {
+        if *hide {
+            "Show deprecated".to_string()
+        } else {
+            "Hide deprecated".to_string()
+        }
+    })
+    .with_text_color(theme::PLACEHOLDER_COLOR)
+    .on_click(|ctx, _, _| ctx.submit_command(TOGGLE_DEPRECATED))
+    .lens(lens!(data::App, hide_deprecated));
+
+    let features = Label::new(|filter: &bool, _: &Env| {
+        if *filter {
+            "All features".to_string()
+        } else {
+            "Filter features".to_string()
+        }
+    })
+    .with_text_color(theme::PLACEHOLDER_COLOR)
+    .on_click(|ctx, _, _| ctx.submit_command(TOGGLE_FEATURE_FILTER))
+    .lens(lens!(data::App, feature_filter))
+    .empty_if(|app: &data::App, _| app.features.is_empty());
+
+    let export = Label::new("Export Markdown")
+        .with_text_color(theme::PLACEHOLDER_COLOR)
+        .on_click(|ctx, _, _| {
+            ctx.submit_command(commands::SHOW_OPEN_PANEL.with(export_dialog()));
+        });
+
+    let deprecated = Label::new("Deprecated items")
+        .with_text_color(theme::PLACEHOLDER_COLOR)
+        .on_click(|ctx, _, _| ctx.submit_command(SHOW_DEPRECATED));
+
+    Flex::row()
+        .with_flex_spacer(1.)
+        .with_child(export)
+        .with_spacer(20.)
+        .with_child(deprecated)
+        .with_spacer(20.)
+        .with_child(hide_deprecated)
+        .with_spacer(20.)
+        .with_child(attr_filter)
+        .with_spacer(20.)
+        .with_child(features)
+        .with_spacer(20.)
+        .with_child(private)
+        .with_spacer(20.)
+        .with_child(paths)
+        .padding((10., 5.))
+        .empty_if(|app: &data::App, _| !matches!(app.page, data::Page::Docs(_)))
+}
+
+/// The crate features, shown while the feature filter is on. Click one to toggle it.
+fn feature_bar() -> impl Widget<data::App> {
+    let features = List::new(|| {
+        Label::new(|f: &data::Feature, _: &Env| f.name.clone())
+            .with_font(theme::CODE_FONT)
+            .padding((4., 1.))
+            .border(theme::TRAIT_COLOR, 1.)
+            .rounded(3.)
+            .dim_if(|f: &data::Feature| !f.enabled)
+            .on_click(|ctx, f: &mut data::Feature, _| {
+                ctx.submit_command(TOGGLE_FEATURE.with(f.name.clone()))
+            })
+            .padding((0., 0., 5., 0.))
+    })
+    .horizontal()
+    .scroll()
+    .horizontal()
+    .lens(lens!(data::App, features));
+
+    Flex::row()
+        .with_child(Label::new("Features:").with_text_color(theme::PLACEHOLDER_COLOR))
+        .with_spacer(10.)
+        .with_flex_child(features, 1.)
+        .padding((10., 0., 10., 5.))
+        .empty_if(|app: &data::App, _| {
+            !app.feature_filter || !matches!(app.page, data::Page::Docs(_))
+        })
+}
+
+fn welcome() -> impl Widget<data::Welcome> {
+    let open = Button::new("Open...").on_click(|ctx, _, _| {
+        ctx.submit_command(commands::SHOW_OPEN_PANEL.with(open_dialog()));
+    });
+
+    // druid has no events for files dropped onto a window
+    let paste_hint = Label::new(
+        "Files can't be dropped here. Copy a JSON file or a project in the file manager \
+         and paste it with Ctrl+V (Cmd+V on macOS) instead.",
+    )
+    .with_line_break_mode(LineBreaking::WordWrap)
+    .with_text_color(theme::PLACEHOLDER_COLOR);
+
+    let recent = List::new(|| {
+        let name = RawLabel::code()
+            .color(theme::MOD_COLOR)
+            .computed(|r: &data::Recent| match &r.version {
+                Some(version) => format!("{} {}", r.name, version),
+                None => r.name.clone(),
+            });
+        let path = Label::new(|r: &data::Recent, _: &Env| r.path.display().to_string())
+            .with_text_color(theme::PLACEHOLDER_COLOR);
+        Flex::column()
+            .cross_axis_alignment(CrossAxisAlignment::Start)
+            .with_child(name)
+            .with_child(path)
+            .padding((0., 5.))
+            .on_click(|ctx, r: &mut data::Recent, _| {
+                ctx.submit_command(OPEN_PATH.with(r.path.clone()));
+            })
+    });
+    let recent = Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(h2("Recent crates"))
+        .seperator(2)
+        .with_child(recent)
+        .empty_if(|r: &Vector<data::Recent>, _| r.is_empty())
+        .lens(lens!(data::Welcome, recent));
+
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(Label::new("Open a rustdoc JSON file or a cargo project").with_text_size(24.))
+        .with_default_spacer()
+        .with_child(open)
+        .with_default_spacer()
+        .with_child(paste_hint)
+        .with_spacer(20.)
+        .with_child(recent)
+        .padding(10.)
+        .scroll()
+        .vertical()
+}
+
+fn loading() -> impl Widget<data::Loading> {
+    let title = Label::new(|l: &data::Loading, _: &Env| l.title.clone()).with_text_size(24.);
+    let progress = Maybe::or_empty(|| {
+        let status = Label::new(|p: &data::Progress, _: &Env| match p {
+            data::Progress::ReadingCache => "Reading cache...".into(),
+            data::Progress::Reading { read, total } => {
+                format!("Reading {} of {}", format_size(*read), format_size(*total))
+            }
+            data::Progress::Parsing => "Parsing...".into(),
+            data::Progress::BuildingPage => "Building the first page...".into(),
+        });
+        let bar = ProgressBar::new().computed(|p: &data::Progress| match p {
+            data::Progress::Reading { read, total } => *read as f64 / (*total).max(1) as f64,
+            _ => 1.,
+        });
+        Flex::column()
+            .cross_axis_alignment(CrossAxisAlignment::Start)
+            .with_child(status)
+            .with_child(bar)
+    })
+    .lens(lens!(data::Loading, progress));
+    let error = RawLabel::code()
+        .color(theme::ERROR_COLOR)
+        .wrap_text()
+        .or_empty()
+        .lens(lens!(data::Loading, error));
+    let log = List::new(|| RawLabel::code().wrap_text()).lens(lens!(data::Loading, log));
+
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(title)
+        .with_default_spacer()
+        .with_child(progress)
+        .with_default_spacer()
+        .with_child(error)
+        .with_default_spacer()
+        .with_child(log)
+        .padding(10.)
+        .scroll()
+        .vertical()
+}
+
+fn format_size(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / (1 << 20) as f64)
+}
+
+fn notification() -> impl Widget<data::App> {
+    Maybe::or_empty(|| {
+        Label::new(|n: &String, _: &Env| n.clone())
+            .padding((10., 5.))
+            .background(theme::BACKGROUND_LIGHT)
+    })
+    .lens(lens!(data::App, notification))
+    .controller(DismissNotification {
+        timer: TimerToken::INVALID,
+    })
+}
+
+/// Clears the notification a while after it was shown.
+struct DismissNotification {
+    timer: TimerToken,
+}
+
+impl<W: Widget<data::App>> Controller<data::App, W> for DismissNotification {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut data::App,
+        env: &Env,
+    ) {
+        if matches!(event, Event::Timer(token) if *token == self.timer) {
+            data.notification = None;
+        }
+        child.event(ctx, event, data, env)
+    }
+
+    fn update(
+        &mut self,
+        child: &mut W,
+        ctx: &mut UpdateCtx,
+        old_data: &data::App,
+        data: &data::App,
+        env: &Env,
+    ) {
+        if data.notification.is_some() && !old_data.notification.same(&data.notification) {
+            self.timer = ctx.request_timer(NOTIFICATION_TIMEOUT);
+        }
+        child.update(ctx, old_data, data, env)
+    }
 }
 
 fn mod_() -> impl Widget<data::Mod> {
@@ -32,11 +326,7 @@ fn mod_() -> impl Widget<data::Mod> {
             r.build()
         });
 
-    let docs = RawLabel::new()
-        .wrap_text()
-        .padding((0., 0., 0., 10.))
-        .or_empty()
-        .lens(lens!(data::Mod, item.doc));
+    let docs = doc_label((0., 0., 0., 10.)).lens(lens!(data::Mod, item.doc));
 
     let mods = item_list("Modules", theme::MOD_COLOR).lens(lens!(data::Mod, mods));
     let structs = item_list("Structs", theme::STRUCT_COLOR).lens(lens!(data::Mod, structs));
@@ -46,6 +336,8 @@ fn mod_() -> impl Widget<data::Mod> {
     Flex::column()
         .cross_axis_alignment(CrossAxisAlignment::Start)
         .with_child(name)
+        .with_child(deprecation_banner().lens(lens!(data::Mod, item.deprecation)))
+        .with_child(cfg_banner().lens(lens!(data::Mod, item.cfg)))
         .with_default_spacer()
         .with_child(docs)
         .with_default_spacer()
@@ -58,22 +350,87 @@ fn mod_() -> impl Widget<data::Mod> {
         .vertical()
 }
 
+fn index() -> impl Widget<data::Index> {
+    let title = Label::new(|i: &data::Index, _: &Env| i.title.clone()).with_text_size(24.);
+    let items = List::new(|| {
+        let path = RawLabel::code()
+            .computed(|it: &data::Item| {
+                let mut r = RichTextBuilder::new();
+                for p in &it.parents {
+                    r.push(p).text_color(theme::MOD_COLOR);
+                    r.push("::");
+                }
+                r.push(&it.name);
+                r.build()
+            })
+            .on_click(|ctx, it: &mut data::Item, _| {
+                ctx.submit_command(GOTO_ITEM.with(it.id.clone()));
+            });
+        Flex::column()
+            .cross_axis_alignment(CrossAxisAlignment::Start)
+            .with_child(path)
+            .with_child(deprecation_banner().lens(lens!(data::Item, deprecation)))
+            .padding((0., 5.))
+    })
+    .lens(lens!(data::Index, items));
+
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(title)
+        .seperator(2)
+        .with_child(items)
+        .padding(10.)
+        .scroll()
+        .vertical()
+}
+
 fn item_list(head: &'static str, color: Key<Color>) -> impl Widget<Vector<data::Item>> {
     let mods = Table::new()
         .seperator(0., 0.)
         .col(move || {
-            RawLabel::code()
+            let name = RawLabel::code()
                 .color(color)
                 .lens(lens!(data::Item, name))
                 .on_click(|ctx, it, _env| {
                     ctx.submit_command(GOTO_ITEM.with(it.id.clone()));
-                })
+                });
+            // `#[doc(cfg(..))]` gets its own badge
+            let attrs = List::new(|| {
+                badge(
+                    Label::new(|attr: &String, _: &Env| attrs::badge(attr)),
+                    theme::PLACEHOLDER_COLOR,
+                )
+            })
+            .horizontal()
+            .computed(|it: &data::Item| {
+                let shown = |a: &&String| attrs::name(a) != Some("doc(cfg)");
+                it.attrs
+                    .iter()
+                    .filter(shown)
+                    .cloned()
+                    .collect::<Vector<_>>()
+            });
+            let deprecated = deprecated_badge().lens(lens!(data::Item, deprecation));
+            let cfg = Maybe::or_empty(|| {
+                badge(
+                    Label::new(|cfg: &Arc<Cfg>, _: &Env| cfg.short()),
+                    theme::TRAIT_COLOR,
+                )
+            })
+            .lens(lens!(data::Item, cfg));
+            Flex::row()
+                .with_child(name)
+                .with_child(deprecated)
+                .with_child(cfg)
+                .with_child(attrs)
+                .dim_if(|it: &data::Item| it.private)
         })
         .col(|| {
             RawLabel::code()
                 .padding((20., 0., 0., 0.))
                 .or_empty()
                 .lens(lens!(data::Item, short_doc))
+                .dim_if(|it: &data::Item| it.private)
         });
 
     Flex::column()
@@ -85,6 +442,51 @@ fn item_list(head: &'static str, color: Key<Color>) -> impl Widget<Vector<data::
         .empty_if(|m, _| m.is_empty())
 }
 
+fn badge<T: Data>(label: Label<T>, color: Key<Color>) -> impl Widget<T> {
+    label
+        .with_text_size(12.)
+        .with_text_color(color.clone())
+        .padding((4., 1.))
+        .border(color, 1.)
+        .rounded(3.)
+        .padding((5., 0., 0., 0.))
+}
+
+/// Marks deprecated items in listings and impls.
+fn deprecated_badge() -> impl Widget<Option<Deprecation>> {
+    badge(Label::new("deprecated"), theme::WARNING_COLOR)
+        .empty_if(|d: &Option<Deprecation>, _| d.is_none())
+}
+
+fn deprecation_banner() -> impl Widget<Option<Deprecation>> {
+    Maybe::or_empty(|| {
+        RawLabel::new()
+            .wrap_text()
+            .computed(|d: &Deprecation| md::markdown_to_text(&md::deprecation_note(d)))
+            .padding((10., 5.))
+            .border(theme::WARNING_COLOR, 1.)
+            .rounded(3.)
+            .padding((0., 5.))
+    })
+}
+
+fn cfg_banner() -> impl Widget<Option<Arc<Cfg>>> {
+    Maybe::or_empty(|| {
+        RawLabel::new()
+            .wrap_text()
+            .computed(|cfg: &Arc<Cfg>| md::markdown_to_text(&cfg.note()))
+            .padding((10., 5.))
+            .border(theme::TRAIT_COLOR, 1.)
+            .rounded(3.)
+            .padding((0., 5.))
+    })
+}
+
+fn doc_label(padding: impl Into<Insets>) -> impl Widget<Option<data::Doc>> {
+    let padding = padding.into();
+    Maybe::or_empty(move || DocLabel::new().padding(padding))
+}
+
 fn h2<T: Data>(txt: &str) -> impl Widget<T> {
     Label::new(txt).with_text_size(21.)
 }
@@ -93,13 +495,13 @@ fn struct_() -> impl Widget<data::Struct> {
     let name = RawLabel::code()
         .with_text_size(24.)
         .computed(|t: &data::Struct| {
-            let mut r = RichTextBuilder::new();
+            let mut r = Code::with_paths(t.item.paths.clone(), false);
             r.push("struct ");
             for i in &t.item.parents {
-                r.push(&i).text_color(theme::MOD_COLOR);
+                r.push(&i).color(Syntax::Mod);
                 r.push("::");
             }
-            r.push(&t.item.name).text_color(theme::STRUCT_COLOR);
+            r.push(&t.item.name).color(Syntax::Struct);
             if !t.generics.params.is_empty() {
                 r.push("<");
                 format_generics_def(&t.generics.params, true, &mut r);
@@ -108,42 +510,27 @@ fn struct_() -> impl Widget<data::Struct> {
             r.build()
         });
 
-    let generics = RawLabel::code()
-        .computed(|t: &data::Struct| {
-            let mut r = RichTextBuilder::new();
-            format_wheres(&t.generics.params, &t.generics.where_predicates, &mut r);
-            r.build()
-        })
-        .empty_if(|t, _| {
-            t.generics.where_predicates.is_empty()
-                && !t.generics.params.iter().any(|i| {
-                    matches!( &i.kind,
-                               GenericParamDefKind::Type { bounds, .. } if !bounds.is_empty())
-                })
-        });
+    let decl = FlowLabel::new(|t: &data::Struct, _, full| {
+        let mut r = Code::with_paths(t.item.paths.clone(), full);
+        format_struct_decl(t, &mut r);
+        r.build()
+    })
+    .padding((0., 10.));
 
-    let docs = RawLabel::new()
-        .wrap_text()
-        .or_empty()
-        .lens(lens!(data::Struct, item.doc));
+    let docs = doc_label(0.).lens(lens!(data::Struct, item.doc));
 
     let feilds = List::new(|| {
-        let docs = RawLabel::new()
-            .wrap_text()
-            .padding((10., 5., 0., 10.))
-            .or_empty()
-            .lens(lens!(data::Field, item.doc));
+        let docs = doc_label((10., 5., 0., 10.)).lens(lens!(data::Field, item.doc));
 
         Flex::column()
             .cross_axis_alignment(CrossAxisAlignment::Start)
-            .with_child(RawLabel::code().computed(|t: &data::Field| {
-                let mut r = RichTextBuilder::new();
-                r.push(&t.item.name);
-                r.push(": ");
-                format_ty(&t.ty, false, &mut r);
+            .with_child(FlowLabel::new(|t: &data::Field, _, full| {
+                let mut r = Code::with_paths(t.item.paths.clone(), full);
+                format_field(t, &mut r);
                 r.build()
             }))
             .with_child(docs)
+            .dim_if(|f: &data::Field| f.item.private)
     });
 
     let fields = Flex::column()
@@ -187,7 +574,9 @@ fn struct_() -> impl Widget<data::Struct> {
     Flex::column()
         .cross_axis_alignment(CrossAxisAlignment::Start)
         .with_child(name)
-        .with_child(generics)
+        .with_child(decl)
+        .with_child(deprecation_banner().lens(lens!(data::Struct, item.deprecation)))
+        .with_child(cfg_banner().lens(lens!(data::Struct, item.cfg)))
         .with_default_spacer()
         .with_child(docs)
         .with_spacer(20.)
@@ -206,13 +595,13 @@ fn enum_() -> impl Widget<data::Enum> {
     let name = RawLabel::code()
         .with_text_size(24.)
         .computed(|t: &data::Enum| {
-            let mut r = RichTextBuilder::new();
+            let mut r = Code::with_paths(t.item.paths.clone(), false);
             r.push("enum ");
             for i in &t.item.parents {
-                r.push(&i).text_color(theme::MOD_COLOR);
+                r.push(&i).color(Syntax::Mod);
                 r.push("::");
             }
-            r.push(&t.item.name).text_color(theme::STRUCT_COLOR);
+            r.push(&t.item.name).color(Syntax::Struct);
             if !t.generics.params.is_empty() {
                 r.push("<");
                 format_generics_def(&t.generics.params, true, &mut r);
@@ -221,19 +610,12 @@ fn enum_() -> impl Widget<data::Enum> {
             r.build()
         });
 
-    let generics = RawLabel::code()
-        .computed(|t: &data::Enum| {
-            let mut r = RichTextBuilder::new();
-            format_wheres(&t.generics.params, &t.generics.where_predicates, &mut r);
-            r.build()
-        })
-        .empty_if(|t, _| {
-            t.generics.where_predicates.is_empty()
-                && !t.generics.params.iter().any(|i| {
-                    matches!( &i.kind,
-                               GenericParamDefKind::Type { bounds, .. } if !bounds.is_empty())
-                })
-        });
+    let decl = FlowLabel::new(|t: &data::Enum, _, full| {
+        let mut r = Code::with_paths(t.item.paths.clone(), full);
+        format_enum_decl(t, &mut r);
+        r.build()
+    })
+    .padding((0., 10.));
 
     let variants = Flex::column()
         .cross_axis_alignment(CrossAxisAlignment::Start)
@@ -244,10 +626,7 @@ fn enum_() -> impl Widget<data::Enum> {
         .empty_if(|f: &Vector<_>, _| f.is_empty())
         .lens(lens!(data::Enum, variants));
 
-    let docs = RawLabel::new()
-        .wrap_text()
-        .or_empty()
-        .lens(lens!(data::Enum, item.doc));
+    let docs = doc_label(0.).lens(lens!(data::Enum, item.doc));
 
     let impls = List::new(impl_).with_spacing(20.);
     let impls = Flex::column()
@@ -281,7 +660,9 @@ fn enum_() -> impl Widget<data::Enum> {
     Flex::column()
         .cross_axis_alignment(CrossAxisAlignment::Start)
         .with_child(name)
-        .with_child(generics)
+        .with_child(decl)
+        .with_child(deprecation_banner().lens(lens!(data::Enum, item.deprecation)))
+        .with_child(cfg_banner().lens(lens!(data::Enum, item.cfg)))
         .with_default_spacer()
         .with_child(docs)
         .with_default_spacer()
@@ -297,42 +678,11 @@ fn enum_() -> impl Widget<data::Enum> {
 }
 
 fn variant() -> impl Widget<data::Variant> {
-    let docs = RawLabel::new()
-        .wrap_text()
-        .padding((10., 5., 0., 10.))
-        .or_empty()
-        .lens(lens!(data::Variant, item.doc));
-
-    let label = RawLabel::code().computed(|v: &data::Variant| {
-        let mut r = RichTextBuilder::new();
-        r.push(&v.item.name).text_color(theme::ENUM_COLOR);
-        match &v.inner {
-            data::VariantInner::Plain => {}
-            data::VariantInner::Tuple(ts) => {
-                r.push("(");
-                format_seperated(ts.iter(), ", ", &mut r, |ty, r| {
-                    format_ty(ty, false, r);
-                });
-                r.push(")");
-            }
-            data::VariantInner::Struct(fs) => {
-                if !fs.is_empty() {
-                    r.push(" {");
-                    r.push("\n");
-                } else {
-                    r.push("{ ");
-                }
-                for f in fs.iter() {
-                    r.push("    ");
-                    r.push(&f.item.name);
-                    r.push(": ");
-                    format_ty(&f.ty, false, &mut r);
-                    r.push(",");
-                    r.push("\n");
-                }
-                r.push("}");
-            }
-        }
+    let docs = doc_label((10., 5., 0., 10.)).lens(lens!(data::Variant, item.doc));
+
+    let label = FlowLabel::new(|v: &data::Variant, _, full| {
+        let mut r = Code::with_paths(v.item.paths.clone(), full);
+        format_variant(v, &mut r);
         r.build()
     });
     Flex::column()
@@ -342,30 +692,9 @@ fn variant() -> impl Widget<data::Variant> {
 }
 
 fn impl_() -> impl Widget<data::Impl> {
-    let head = RawLabel::code().computed(|i: &data::Impl| {
-        let mut r = RichTextBuilder::new();
-        r.push("impl");
-        if !i.generics.params.is_empty() {
-            r.push("<");
-            format_generics_def(&i.generics.params, true, &mut r);
-            r.push(">");
-        }
-
-        r.push(" ");
-        if let Some(tr) = &i.trait_ {
-            format_ty(tr, true, &mut r);
-            r.push(" for ");
-        }
-        format_ty(&i.for_, false, &mut r);
-        if !i.generics.where_predicates.is_empty()
-            || i.generics.params.iter().any(|i| {
-                matches!( &i.kind,
-                GenericParamDefKind::Type { bounds, .. } if !bounds.is_empty())
-            })
-        {
-            r.push("\n");
-            format_wheres(&i.generics.params, &i.generics.where_predicates, &mut r);
-        }
+    let head = FlowLabel::new(|i: &data::Impl, _, full| {
+        let mut r = Code::with_paths(i.item.paths.clone(), full);
+        format_impl_head(i, &mut r);
         r.build()
     });
     let fns = List::new(impl_fn)
@@ -395,136 +724,91 @@ fn impl_() -> impl Widget<data::Impl> {
 }
 
 fn impl_fn() -> impl Widget<data::Fn> {
-    let docs = RawLabel::new()
-        .wrap_text()
-        .padding((10., 10., 0., 10.))
-        .or_empty()
-        .lens(lens!(data::Fn, item.doc));
+    let signature = FlowLabel::new(|f: &data::Fn, width, full| {
+        let mut r = Code::with_paths(f.item.paths.clone(), full);
+        format_fn(f, false, width, &mut r);
+        r.build()
+    });
+
+    let docs = doc_label((10., 10., 0., 10.)).lens(lens!(data::Fn, item.doc));
 
     Flex::column()
         .cross_axis_alignment(CrossAxisAlignment::Start)
-        .with_child(RawLabel::code().computed(|f: &data::Fn| {
-            let mut r = RichTextBuilder::new();
-            format_fn(&f.item.name, &f.header, &f.generics, &f.decl, &mut r);
-            r.build()
-        }))
+        .with_child(
+            Flex::row()
+                .cross_axis_alignment(CrossAxisAlignment::Start)
+                .with_flex_child(signature, 1.)
+                .with_child(deprecated_badge().lens(lens!(data::Fn, item.deprecation))),
+        )
+        .with_child(deprecation_banner().lens(lens!(data::Fn, item.deprecation)))
+        .with_child(cfg_banner().lens(lens!(data::Fn, item.cfg)))
         .with_child(docs)
         .padding((20., 0., 0., 0.))
+        .dim_if(|f: &data::Fn| f.item.private)
 }
 
 fn impl_const() -> impl Widget<data::Const> {
-    let docs = RawLabel::new()
-        .wrap_text()
-        .padding((10., 10., 0., 10.))
-        .or_empty()
-        .lens(lens!(data::Const, item.doc));
+    let signature = FlowLabel::new(|c: &data::Const, _, full| {
+        let mut r = Code::with_paths(c.item.paths.clone(), full);
+        format_const(c, &mut r);
+        r.build()
+    });
+
+    let docs = doc_label((10., 10., 0., 10.)).lens(lens!(data::Const, item.doc));
 
     Flex::column()
         .cross_axis_alignment(CrossAxisAlignment::Start)
-        .with_child(RawLabel::code().computed(|f: &data::Const| {
-            let mut r = RichTextBuilder::new();
-            r.push("const ");
-            r.push(&f.item.name).text_color(theme::CONST_COLOR);
-            r.push(": ");
-            format_ty(&f.ty, false, &mut r);
-            r.build()
-        }))
+        .with_child(
+            Flex::row()
+                .cross_axis_alignment(CrossAxisAlignment::Start)
+                .with_flex_child(signature, 1.)
+                .with_child(deprecated_badge().lens(lens!(data::Const, item.deprecation))),
+        )
+        .with_child(deprecation_banner().lens(lens!(data::Const, item.deprecation)))
+        .with_child(cfg_banner().lens(lens!(data::Const, item.cfg)))
         .with_child(docs)
         .padding((20., 0., 0., 0.))
+        .dim_if(|c: &data::Const| c.item.private)
 }
 fn impl_type_def() -> impl Widget<data::TypeDef> {
-    let docs = RawLabel::new()
-        .wrap_text()
-        .padding((10., 10., 0., 10.))
-        .or_empty()
-        .lens(lens!(data::TypeDef, item.doc));
+    let signature = FlowLabel::new(|t: &data::TypeDef, _, full| {
+        let mut r = Code::with_paths(t.item.paths.clone(), full);
+        format_type_def(t, &mut r);
+        r.build()
+    });
+
+    let docs = doc_label((10., 10., 0., 10.)).lens(lens!(data::TypeDef, item.doc));
 
     Flex::column()
         .cross_axis_alignment(CrossAxisAlignment::Start)
-        .with_child(RawLabel::code().computed(|t: &data::TypeDef| {
-            let mut r = RichTextBuilder::new();
-            r.push("type ");
-            r.push(&t.item.name).text_color(theme::TYPE_COLOR);
-            r.push(" = ");
-            format_ty(&t.ty, false, &mut r);
-            r.build()
-        }))
+        .with_child(
+            Flex::row()
+                .cross_axis_alignment(CrossAxisAlignment::Start)
+                .with_flex_child(signature, 1.)
+                .with_child(deprecated_badge().lens(lens!(data::TypeDef, item.deprecation))),
+        )
+        .with_child(deprecation_banner().lens(lens!(data::TypeDef, item.deprecation)))
+        .with_child(cfg_banner().lens(lens!(data::TypeDef, item.cfg)))
         .with_child(docs)
         .padding((20., 0., 0., 0.))
+        .dim_if(|t: &data::TypeDef| t.item.private)
 }
 
 fn fn_() -> impl Widget<data::Fn> {
-    let name = RawLabel::code()
-        .with_text_size(21.)
-        .computed(|f: &data::Fn| {
-            let mut r = RichTextBuilder::new();
-            r.push("fn ");
-            for i in &f.item.parents {
-                r.push(&i).text_color(theme::MOD_COLOR);
-                r.push("::");
-            }
-            r.push(&f.item.name).text_color(theme::FN_COLOR);
-
-            if !f
-                .generics
-                .params
-                .iter()
-                .all(|x| x.name.starts_with("impl "))
-            {
-                r.push("<");
-                format_generics_def(
-                    f.generics
-                        .params
-                        .iter()
-                        .filter(|x| !x.name.starts_with("impl ")),
-                    true,
-                    &mut r,
-                );
-                r.push(">");
-            }
-
-            r.push("(\n");
-            for (name, ty) in &f.decl.inputs {
-                r.push("    ");
-                r.push(&name);
-                r.push(": ");
-                format_ty(ty, false, &mut r);
-                r.push(", ");
-                r.push("\n");
-            }
-            r.push(")");
-            if let Some(ty) = &f.decl.output {
-                r.push(" -> ");
-                format_ty(ty, false, &mut r);
-            }
-
-            r.build()
-        });
-
-    let generics = RawLabel::code()
-        .computed(|t: &data::Fn| {
-            let mut r = RichTextBuilder::new();
-            format_wheres(&t.generics.params, &t.generics.where_predicates, &mut r);
-            r.build()
-        })
-        .empty_if(|t, _| {
-            t.generics.where_predicates.is_empty()
-                && !t.generics.params.iter().any(|f| {
-                    !f.name.starts_with("impl ")
-                        && matches!( &f.kind,
-                               GenericParamDefKind::Type { bounds, .. } if !bounds.is_empty())
-                })
-        });
+    let name = FlowLabel::new(|f: &data::Fn, width, full| {
+        let mut r = Code::with_paths(f.item.paths.clone(), full);
+        format_fn(f, true, width, &mut r);
+        r.build()
+    })
+    .with_text_size(21.);
 
-    let docs = RawLabel::new()
-        .wrap_text()
-        .or_empty()
-        .lens(lens!(data::Fn, item.doc));
+    let docs = doc_label(0.).lens(lens!(data::Fn, item.doc));
 
     Flex::column()
         .cross_axis_alignment(CrossAxisAlignment::Start)
         .with_child(name)
-        .with_child(generics)
+        .with_child(deprecation_banner().lens(lens!(data::Fn, item.deprecation)))
+        .with_child(cfg_banner().lens(lens!(data::Fn, item.cfg)))
         .with_default_spacer()
         .with_child(docs)
         .with_default_spacer()
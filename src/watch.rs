@@ -0,0 +1,66 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use druid::{ExtEventSink, Selector, SingleUse, Target};
+use rustdoc_types as rdoc;
+
+use crate::load::read_crate;
+
+/// A crate reloaded after its file changed, with the generation of the crate it replaces.
+pub const RELOAD: Selector<(usize, SingleUse<rdoc::Crate>)> = Selector::new("druid-rustdoc.reload");
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The generation of the crate that is open, watchers of other generations stop.
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+
+/// Reparses the JSON at `path` on a background thread whenever it changes, and submits the new
+/// crate as [`RELOAD`]. Watches as long as the crate of `generation` is open.
+pub fn watch(path: PathBuf, generation: usize, sink: ExtEventSink) {
+    thread::spawn(move || {
+        let mut last = modified(&path);
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            if CURRENT.load(Ordering::SeqCst) != generation {
+                break;
+            }
+            let current = modified(&path);
+            if current.is_none() || current == last {
+                continue;
+            }
+            last = current;
+            // rustdoc might still be writing, wait until the file settles
+            thread::sleep(POLL_INTERVAL);
+            if modified(&path) != last {
+                continue;
+            }
+
+            let krate = read_crate(&path);
+            if CURRENT.load(Ordering::SeqCst) != generation {
+                break;
+            }
+            match krate {
+                Ok(krate) => {
+                    let reload = (generation, SingleUse::new(krate));
+                    if sink.submit_command(RELOAD, reload, Target::Auto).is_err() {
+                        // the app is gone
+                        break;
+                    }
+                }
+                Err(e) => log::warn!("failed to reload {}: {}", path.display(), e),
+            }
+        }
+    });
+}
+
+/// Stops the watchers of other crates because the crate of `generation` is opened.
+pub fn stop_others(generation: usize) {
+    CURRENT.store(generation, Ordering::SeqCst);
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
@@ -1,10 +1,16 @@
-use druid::piet::StrokeStyle;
-use druid::text::TextStorage;
-use druid::widget::{Either, Flex, LineBreaking, Maybe, RawLabel, SizedBox};
-use druid::{Color, Data, Env, KeyOrValue, Widget, WidgetExt};
+use druid::piet::{StrokeStyle, Text, TextLayout, TextLayoutBuilder};
+use druid::text::{RichText, TextStorage};
+use druid::widget::{Either, EnvScope, Flex, LineBreaking, Maybe, RawLabel, SizedBox};
+use druid::{
+    BoxConstraints, Color, Data, Env, Event, EventCtx, KeyOrValue, LayoutCtx, LifeCycle,
+    LifeCycleCtx, PaintCtx, Selector, Size, UpdateCtx, Widget, WidgetExt,
+};
 use druid_widget_nursery::{ComputedWidget, Seperator};
 
-use crate::theme;
+use crate::{data, md, theme};
+
+const RENDER_DOC: Selector = Selector::new("druid-rustdoc.render-doc");
+const REFLOW: Selector = Selector::new("druid-rustdoc.reflow");
 
 pub trait ComputedExt<T: Data>: Widget<T> + Sized + 'static {
     fn computed<U>(self, f: impl Fn(&U) -> T + 'static) -> ComputedWidget<U, T> {
@@ -20,6 +26,17 @@ pub trait ComputedExt<T: Data>: Widget<T> + Sized + 'static {
     fn empty_if(self, f: impl Fn(&T, &Env) -> bool + 'static) -> Either<T> {
         Either::new(f, SizedBox::empty(), self)
     }
+
+    fn dim_if(self, f: impl Fn(&T) -> bool + 'static) -> EnvScope<T, Self> {
+        EnvScope::new(
+            move |env, data| {
+                if f(data) {
+                    theme::dim(env)
+                }
+            },
+            self,
+        )
+    }
 }
 
 impl<T: Data, W: Widget<T> + 'static> ComputedExt<T> for W {}
@@ -61,3 +78,180 @@ fn seperator<T: Data>(level: u8) -> impl Widget<T> {
         _ => unreachable!(),
     }
 }
+
+/// Shows the docs of an item, rendering the markdown only once the label is laid out.
+pub struct DocLabel {
+    label: RawLabel<RichText>,
+    /// The text `label` currently shows.
+    text: RichText,
+    /// The text for `label`, empty until rendered.
+    next: RichText,
+    rendered: bool,
+}
+
+impl DocLabel {
+    pub fn new() -> Self {
+        Self {
+            label: RawLabel::new().wrap_text(),
+            text: RichText::new("".into()),
+            next: RichText::new("".into()),
+            rendered: false,
+        }
+    }
+}
+
+impl Default for DocLabel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Widget<data::Doc> for DocLabel {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut data::Doc, env: &Env) {
+        match event {
+            Event::Command(cmd) if cmd.is(RENDER_DOC) => {
+                self.next = md::render_doc(data);
+                self.rendered = true;
+                ctx.request_update();
+                ctx.set_handled();
+            }
+            _ => self.label.event(ctx, event, &mut self.text, env),
+        }
+    }
+
+    fn lifecycle(
+        &mut self,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        _data: &data::Doc,
+        env: &Env,
+    ) {
+        self.label.lifecycle(ctx, event, &self.text, env)
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &data::Doc, data: &data::Doc, env: &Env) {
+        if !old_data.same(data) {
+            self.next = RichText::new("".into());
+            self.rendered = false;
+        }
+        self.label.update(ctx, &self.text, &self.next, env);
+        self.text = self.next.clone();
+    }
+
+    fn layout(
+        &mut self,
+        ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        _data: &data::Doc,
+        env: &Env,
+    ) -> Size {
+        if !self.rendered {
+            ctx.submit_command(RENDER_DOC.to(ctx.widget_id()));
+        }
+        self.label.layout(ctx, bc, &self.text, env)
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, _data: &data::Doc, env: &Env) {
+        self.label.paint(ctx, &self.text, env)
+    }
+}
+
+/// A code label formatted for the number of columns it has, re-formatted when its width changes.
+/// While hovered it shows full paths, the format function is told with its last argument.
+pub struct FlowLabel<T> {
+    label: RawLabel<RichText>,
+    text: RichText,
+    next: RichText,
+    format: Box<dyn Fn(&T, usize, bool) -> RichText>,
+    text_size: Option<f64>,
+    /// Columns the text was formatted for, `usize::MAX` before the first layout.
+    columns: usize,
+    hovered: bool,
+}
+
+impl<T> FlowLabel<T> {
+    pub fn new(format: impl Fn(&T, usize, bool) -> RichText + 'static) -> Self {
+        Self {
+            label: RawLabel::code(),
+            text: RichText::new("".into()),
+            next: RichText::new("".into()),
+            format: Box::new(format),
+            text_size: None,
+            columns: usize::MAX,
+            hovered: false,
+        }
+    }
+
+    pub fn with_text_size(mut self, size: f64) -> Self {
+        self.label.set_text_size(size);
+        self.text_size = Some(size);
+        self
+    }
+
+    fn format(&self, data: &T) -> RichText {
+        (self.format)(data, self.columns, self.hovered)
+    }
+
+    /// How many characters of the code font fit in `width`.
+    fn columns(&self, ctx: &mut LayoutCtx, width: f64, env: &Env) -> usize {
+        let font = env.get(theme::CODE_FONT);
+        let size = self.text_size.unwrap_or(font.size);
+        let char_width = ctx
+            .text()
+            .new_text_layout("0")
+            .font(font.family, size)
+            .build()
+            .map(|l| l.size().width)
+            .unwrap_or(size * 0.6);
+        (width / char_width) as usize
+    }
+}
+
+impl<T: Data> Widget<T> for FlowLabel<T> {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        match event {
+            Event::Command(cmd) if cmd.is(REFLOW) => {
+                self.next = self.format(data);
+                ctx.request_update();
+                ctx.set_handled();
+            }
+            _ => self.label.event(ctx, event, &mut self.text, env),
+        }
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        match event {
+            LifeCycle::WidgetAdded => {
+                self.text = self.format(data);
+                self.next = self.text.clone();
+            }
+            LifeCycle::HotChanged(hot) => {
+                self.hovered = *hot;
+                ctx.submit_command(REFLOW.to(ctx.widget_id()));
+            }
+            _ => {}
+        }
+        self.label.lifecycle(ctx, event, &self.text, env)
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &T, data: &T, env: &Env) {
+        if !old_data.same(data) {
+            self.next = self.format(data);
+        }
+        self.label.update(ctx, &self.text, &self.next, env);
+        self.text = self.next.clone();
+    }
+
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, _data: &T, env: &Env) -> Size {
+        let columns = self.columns(ctx, bc.max().width, env);
+        if columns != self.columns {
+            self.columns = columns;
+            ctx.submit_command(REFLOW.to(ctx.widget_id()));
+        }
+        self.label.layout(ctx, bc, &self.text, env)
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, _data: &T, env: &Env) {
+        self.label.paint(ctx, &self.text, env)
+    }
+}